@@ -51,8 +51,12 @@ fn main() {
     }
 
     println!("building proof...");
-    let proof = stark.prove(&trace, &transition_constraints, &boundary_constraints);
+    let proof = stark
+        .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+        .unwrap();
     println!("verifying proof...");
-    stark.verify(&proof, &transition_constraints, &boundary_constraints);
+    stark
+        .verify(&proof, &transition_constraints, &boundary_constraints, &[])
+        .unwrap();
     println!("proof valid!");
 }
@@ -50,9 +50,13 @@ fn main() {
 
     let now = Instant::now();
 
-    let proof = stark.prove(&trace, &transition_constraints, &boundary_constraints);
+    let proof = stark
+        .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+        .unwrap();
 
     println!("Proving time: {:.2?}", now.elapsed());
 
-    stark.verify(&proof, &transition_constraints, &boundary_constraints);
+    stark
+        .verify(&proof, &transition_constraints, &boundary_constraints, &[])
+        .unwrap();
 }
@@ -1,42 +1,242 @@
+use crate::field_element::FieldElement;
+use crate::tree::{Blake3Hasher, KeccakHasher};
 use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::cell::Cell;
+use std::marker::PhantomData;
 
 #[derive(Serialize, Deserialize)]
 pub struct Message {
     pub data: Vec<u8>,
+    // domain separation, absorbed before the data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
 }
 
-#[derive(Default)]
-pub struct Channel {
+impl Message {
+    // the bytes a hasher absorbs for this message, the label
+    // is length prefixed so it can't run into the data
+    fn absorb(&self, mut update: impl FnMut(&[u8])) {
+        if let Some(label) = &self.label {
+            update(&u32::try_from(label.len()).unwrap().to_le_bytes());
+            update(label.as_bytes());
+        }
+        update(&self.data);
+    }
+}
+
+// hash function used for fiat-shamir, the prover and
+// verifier must use the same one
+pub trait ChannelHasher {
+    // public inputs are absorbed before any message
+    fn hash_messages(public: &[u8], messages: &[Message]) -> [u8; 32];
+}
+
+impl ChannelHasher for Blake3Hasher {
+    fn hash_messages(public: &[u8], messages: &[Message]) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(public);
+        for msg in messages {
+            msg.absorb(|bytes| {
+                hasher.update(bytes);
+            });
+        }
+        *hasher.finalize().as_bytes()
+    }
+}
+
+impl ChannelHasher for KeccakHasher {
+    fn hash_messages(public: &[u8], messages: &[Message]) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(public);
+        for msg in messages {
+            msg.absorb(|bytes| hasher.update(bytes));
+        }
+        hasher.finalize().into()
+    }
+}
+
+// which side of the transcript a channel is used for,
+// Both allows proving and verifying on one channel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+enum Mode {
+    Prover,
+    Verifier,
+    #[default]
+    Both,
+}
+
+// serialized with the read position so a transcript can
+// be resumed, see to_bytes
+#[derive(Serialize, Deserialize)]
+pub struct Channel<H: ChannelHasher = Blake3Hasher> {
     pub messages: Vec<Message>,
     read_index: usize,
+    #[serde(default)]
+    mode: Mode,
+    // bytes every challenge depends on, see absorb_public
+    #[serde(default)]
+    public: Vec<u8>,
+    // set once a challenge has been derived
+    #[serde(default)]
+    sampled: Cell<bool>,
+    #[serde(skip)]
+    hasher: PhantomData<H>,
+}
+
+impl<H: ChannelHasher> Default for Channel<H> {
+    fn default() -> Self {
+        Self::with_hasher()
+    }
 }
 
 impl Channel {
     pub fn new() -> Channel {
+        Self::with_hasher()
+    }
+
+    // only push and prover_hash may be used
+    pub fn new_prover() -> Channel {
+        Self::prover_with_hasher()
+    }
+
+    // only pull and verifier_hash may be used
+    pub fn new_verifier(data: &str) -> Channel {
+        Self::deserialize(data)
+    }
+}
+
+impl<H: ChannelHasher> Channel<H> {
+    pub fn with_hasher() -> Channel<H> {
+        Self::with_mode(Mode::Both)
+    }
+
+    pub fn prover_with_hasher() -> Channel<H> {
+        Self::with_mode(Mode::Prover)
+    }
+
+    fn with_mode(mode: Mode) -> Channel<H> {
         Channel {
             messages: Vec::new(),
             read_index: 0,
+            mode,
+            public: Vec::new(),
+            sampled: Cell::new(false),
+            hasher: PhantomData,
         }
     }
 
+    // public inputs aren't part of the transcript, the prover
+    // and verifier must each absorb the same bytes before
+    // the first challenge is sampled
+    pub fn absorb_public(&mut self, bytes: &[u8]) {
+        if self.sampled.get() {
+            panic!("public inputs must be absorbed before sampling");
+        }
+        self.public.extend_from_slice(bytes);
+    }
+
+    // number of messages pushed
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    // total bytes of message data, labels not included
+    pub fn byte_len(&self) -> usize {
+        self.messages.iter().map(|m| m.data.len()).sum()
+    }
+
+    // clear the transcript and public inputs, the mode and
+    // hasher are kept
+    pub fn reset(&mut self) {
+        self.messages.clear();
+        self.read_index = 0;
+        self.public.clear();
+        self.sampled.set(false);
+    }
+
+    fn push_message(&mut self, msg: Message) {
+        debug_assert!(self.mode != Mode::Verifier, "push on a verifier channel");
+        self.messages.push(msg);
+    }
+
     pub fn push(&mut self, message: &[[u8; 32]]) {
-        let msg = Message {
+        self.push_message(Message {
             data: message.iter().copied().flatten().collect::<Vec<u8>>(),
-        };
-        self.messages.push(msg);
+            label: None,
+        });
     }
 
     pub fn push_single(&mut self, message: &[u8; 32]) {
         self.push(&[*message])
     }
 
+    pub fn push_labeled(&mut self, label: &str, message: &[[u8; 32]]) {
+        self.push_message(Message {
+            data: message.iter().copied().flatten().collect::<Vec<u8>>(),
+            label: Some(label.to_string()),
+        });
+    }
+
+    pub fn push_single_labeled(&mut self, label: &str, message: &[u8; 32]) {
+        self.push_labeled(label, &[*message])
+    }
+
     pub fn pull(&mut self) -> &Message {
+        debug_assert!(self.mode != Mode::Prover, "pull on a prover channel");
         // will panic if pulling past end of message vec
         let m = &self.messages[self.read_index];
         self.read_index += 1;
         m
     }
 
+    // None past the end of the messages, for reading a proof
+    // that may be truncated
+    pub fn try_pull(&mut self) -> Option<&Message> {
+        let m = self.messages.get(self.read_index)?;
+        self.read_index += 1;
+        Some(m)
+    }
+
+    pub fn try_pull_root(&mut self) -> Option<[u8; 32]> {
+        self.try_pull()?.data.clone().try_into().ok()
+    }
+
+    pub fn try_pull_path(&mut self) -> Option<Vec<[u8; 32]>> {
+        let m = self.try_pull()?;
+        if m.data.len() % 32 != 0 {
+            return None;
+        }
+        Some(m.data.chunks(32).map(|d| d.try_into().unwrap()).collect())
+    }
+
+    // returns None without advancing if the next message
+    // wasn't pushed with this label, or there is none
+    pub fn pull_labeled(&mut self, label: &str) -> Option<&Message> {
+        if self.messages.get(self.read_index)?.label.as_deref() != Some(label) {
+            return None;
+        }
+        Some(self.pull())
+    }
+
+    // None if the next message isn't a single element pushed
+    // with this label
+    pub fn pull_labeled_root(&mut self, label: &str) -> Option<[u8; 32]> {
+        self.pull_labeled(label)?.data.clone().try_into().ok()
+    }
+
+    pub fn pull_labeled_path(&mut self, label: &str) -> Option<Vec<[u8; 32]>> {
+        let m = self.pull_labeled(label)?;
+        if m.data.len() % 32 != 0 {
+            return None;
+        }
+        Some(m.data.chunks(32).map(|d| d.try_into().unwrap()).collect())
+    }
+
     pub fn pull_root(&mut self) -> [u8; 32] {
         let m = self.pull();
         m.data.clone().try_into().unwrap()
@@ -51,41 +251,106 @@ impl Channel {
         out
     }
 
-    pub fn prover_hash(&self) -> [u8; 32] {
-        let mut hasher = blake3::Hasher::new();
-        for msg in &self.messages {
-            for v in &msg.data {
-                hasher.update(&v.to_le_bytes());
-            }
+    pub fn push_root(&mut self, root: &[u8; 32]) {
+        self.push_single(root)
+    }
+
+    pub fn push_field<T: FieldElement>(&mut self, v: &T) {
+        self.push_single(&v.to_bytes_le_sized())
+    }
+
+    // will panic if the message isn't a single element
+    pub fn pull_field<T: FieldElement>(&mut self, p: &T::ParamsType) -> T {
+        T::from_bytes_le(&self.pull_root(), p)
+    }
+
+    // three elements in one message, e.g. the points of a
+    // colinearity test
+    pub fn push_triple<T: FieldElement>(&mut self, v: &[T; 3]) {
+        self.push(&[
+            v[0].to_bytes_le_sized(),
+            v[1].to_bytes_le_sized(),
+            v[2].to_bytes_le_sized(),
+        ])
+    }
+
+    // will panic if the message isn't three elements
+    pub fn pull_triple<T: FieldElement>(&mut self, p: &T::ParamsType) -> [T; 3] {
+        let m = self.pull_path();
+        if m.len() != 3 {
+            panic!("expected a message of 3 elements, got {}", m.len());
         }
-        *hasher.finalize().as_bytes()
+        [
+            T::from_bytes_le(&m[0], p),
+            T::from_bytes_le(&m[1], p),
+            T::from_bytes_le(&m[2], p),
+        ]
+    }
+
+    pub fn try_pull_triple<T: FieldElement>(&mut self, p: &T::ParamsType) -> Option<[T; 3]> {
+        let m: [[u8; 32]; 3] = self.try_pull_path()?.try_into().ok()?;
+        Some(m.map(|v| T::from_bytes_le(&v, p)))
+    }
+
+    pub fn prover_hash(&self) -> [u8; 32] {
+        // a verifier would hash messages it hasn't read yet
+        debug_assert!(
+            self.mode != Mode::Verifier,
+            "prover_hash on a verifier channel"
+        );
+        self.sampled.set(true);
+        H::hash_messages(&self.public, &self.messages)
     }
 
     pub fn verifier_hash(&self) -> [u8; 32] {
-        let mut hasher = blake3::Hasher::new();
-        for msg in &self.messages[0..self.read_index] {
-            for v in &msg.data {
-                hasher.update(&v.to_le_bytes());
-            }
-        }
-        *hasher.finalize().as_bytes()
+        debug_assert!(
+            self.mode != Mode::Prover,
+            "verifier_hash on a prover channel"
+        );
+        self.sampled.set(true);
+        H::hash_messages(&self.public, &self.messages[0..self.read_index])
     }
 
     pub fn serialize(&self) -> String {
         serde_json::to_string(&self.messages).unwrap()
     }
 
-    pub fn deserialize(data: &str) -> Channel {
-        Channel {
-            messages: serde_json::from_str(data).unwrap(),
-            read_index: 0,
+    // snapshot of the whole channel state, unlike serialize
+    // which only includes the messages
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Channel<H>> {
+        let channel: Channel<H> = serde_json::from_slice(bytes).ok()?;
+        if channel.read_index > channel.messages.len() {
+            return None;
         }
+        Some(channel)
+    }
+
+    pub fn deserialize(data: &str) -> Channel<H> {
+        Self::try_deserialize(data).unwrap()
+    }
+
+    // None if data isn't a serialized message list
+    pub fn try_deserialize(data: &str) -> Option<Channel<H>> {
+        Some(Channel {
+            messages: serde_json::from_str(data).ok()?,
+            read_index: 0,
+            mode: Mode::Verifier,
+            public: Vec::new(),
+            sampled: Cell::new(false),
+            hasher: PhantomData,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::field_element::{CryptoBigIntElement, ParamWrapper, UC};
     use crate::tree::u128_to_bytes;
+    use crypto_bigint::modular::runtime_mod::DynResidueParams;
 
     use super::*;
 
@@ -126,6 +391,24 @@ mod tests {
         assert_eq!(start, next);
     }
 
+    #[test]
+    fn should_try_pull_malformed_messages() {
+        let mut c = Channel::new();
+        assert!(c.try_pull().is_none());
+
+        c.push(&[[1; 32], [2; 32]]);
+        c.push_message(Message {
+            data: vec![3; 33],
+            label: None,
+        });
+        // two words aren't a root, but are a path
+        assert_eq!(c.try_pull_root(), None);
+        c.read_index = 0;
+        assert_eq!(c.try_pull_path(), Some(vec![[1; 32], [2; 32]]));
+        assert_eq!(c.try_pull_path(), None);
+        assert_eq!(c.try_pull_path(), None);
+    }
+
     #[test]
     fn should_push_pull_message() {
         let mut c = Channel::new();
@@ -153,4 +436,221 @@ mod tests {
         // try to pull another
         c.pull();
     }
+
+    #[test]
+    fn should_hash_with_keccak() {
+        let mut blake3_channel = Channel::new();
+        let mut keccak_channel = Channel::<KeccakHasher>::with_hasher();
+        for x in 0..4 {
+            blake3_channel.push_single(&u128_to_bytes(&x));
+            keccak_channel.push_single(&u128_to_bytes(&x));
+        }
+        assert_ne!(blake3_channel.prover_hash(), keccak_channel.prover_hash());
+
+        let mut deserialized = Channel::<KeccakHasher>::deserialize(&keccak_channel.serialize());
+        for _ in 0..4 {
+            deserialized.pull();
+        }
+        assert_eq!(deserialized.verifier_hash(), keccak_channel.prover_hash());
+    }
+
+    #[test]
+    fn should_agree_on_prover_hash() {
+        fn hash_sequence<H: ChannelHasher>() -> [u8; 32] {
+            let mut c = Channel::<H>::with_hasher();
+            for x in 0..6 {
+                c.push_single(&u128_to_bytes(&x));
+            }
+            c.push(&[u128_to_bytes(&6), u128_to_bytes(&7)]);
+            c.prover_hash()
+        }
+        let blake3 = hash_sequence::<Blake3Hasher>();
+        let keccak = hash_sequence::<KeccakHasher>();
+        assert_eq!(blake3, hash_sequence::<Blake3Hasher>());
+        assert_eq!(keccak, hash_sequence::<KeccakHasher>());
+        assert_ne!(blake3, keccak);
+    }
+
+    #[test]
+    fn should_resume_from_bytes() {
+        let mut c = Channel::<KeccakHasher>::with_hasher();
+        for x in 0..5 {
+            c.push_single(&u128_to_bytes(&x));
+        }
+        c.push(&[u128_to_bytes(&5), u128_to_bytes(&6)]);
+        c.pull();
+        c.pull();
+
+        let mut resumed = Channel::<KeccakHasher>::from_bytes(&c.to_bytes()).unwrap();
+        assert_eq!(resumed.verifier_hash(), c.verifier_hash());
+        assert_eq!(resumed.prover_hash(), c.prover_hash());
+        for _ in 0..4 {
+            assert_eq!(resumed.pull().data, c.pull().data);
+        }
+        assert_eq!(resumed.verifier_hash(), c.verifier_hash());
+
+        assert!(Channel::<KeccakHasher>::from_bytes(b"not a channel").is_none());
+    }
+
+    #[test]
+    fn should_push_pull_field_elements() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let triple = [
+            CryptoBigIntElement::from_u32(7, &p),
+            CryptoBigIntElement::from_u32(3221225472, &p),
+            CryptoBigIntElement::from_u32(0, &p),
+        ];
+        let single = CryptoBigIntElement::from_u32(99, &p);
+
+        let mut c = Channel::new();
+        c.push_triple(&triple);
+        c.push_field(&single);
+        c.push_root(&u128_to_bytes(&5));
+        assert_eq!(c.pull_triple::<CryptoBigIntElement>(&p), triple);
+        assert_eq!(c.pull_field::<CryptoBigIntElement>(&p), single);
+        assert_eq!(c.pull_root(), u128_to_bytes(&5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_fail_to_pull_triple_from_single() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let mut c = Channel::new();
+        c.push_field(&CryptoBigIntElement::from_u32(99, &p));
+        c.pull_triple::<CryptoBigIntElement>(&p);
+    }
+
+    #[test]
+    fn should_try_pull_triple() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let triple = [
+            CryptoBigIntElement::from_u32(7, &p),
+            CryptoBigIntElement::from_u32(8, &p),
+            CryptoBigIntElement::from_u32(9, &p),
+        ];
+        let mut c = Channel::new();
+        c.push_triple(&triple);
+        c.push_field(&CryptoBigIntElement::from_u32(99, &p));
+        assert_eq!(c.try_pull_triple::<CryptoBigIntElement>(&p), Some(triple));
+        assert_eq!(c.try_pull_triple::<CryptoBigIntElement>(&p), None);
+        assert_eq!(c.try_pull_triple::<CryptoBigIntElement>(&p), None);
+    }
+
+    #[test]
+    fn should_pull_labeled_root_and_path() {
+        let mut c = Channel::new();
+        c.push_single_labeled("root", &[1; 32]);
+        c.push_labeled("path", &[[2; 32], [3; 32]]);
+        c.push_labeled("root", &[[4; 32], [5; 32]]);
+        assert_eq!(c.pull_labeled_root("path"), None);
+        assert_eq!(c.pull_labeled_root("root"), Some([1; 32]));
+        assert_eq!(c.pull_labeled_path("path"), Some(vec![[2; 32], [3; 32]]));
+        // labeled correctly but two elements
+        assert_eq!(c.pull_labeled_root("root"), None);
+        assert_eq!(c.pull_labeled_path("root"), None);
+    }
+
+    #[test]
+    fn should_separate_labeled_messages() {
+        let mut unlabeled = Channel::new();
+        let mut first = Channel::new();
+        let mut second = Channel::new();
+        unlabeled.push_single(&u128_to_bytes(&1));
+        first.push_single_labeled("fri", &u128_to_bytes(&1));
+        second.push_single_labeled("stark", &u128_to_bytes(&1));
+        assert_ne!(first.prover_hash(), second.prover_hash());
+        assert_ne!(first.prover_hash(), unlabeled.prover_hash());
+
+        assert!(first.pull_labeled("stark").is_none());
+        assert_eq!(
+            first.pull_labeled("fri").unwrap().data,
+            u128_to_bytes(&1).to_vec()
+        );
+        assert_eq!(first.verifier_hash(), first.prover_hash());
+        assert!(unlabeled.pull_labeled("fri").is_none());
+
+        let mut restored: Channel = Channel::deserialize(&second.serialize());
+        restored.pull_labeled("stark").unwrap();
+        assert_eq!(restored.verifier_hash(), second.prover_hash());
+        assert!(restored.pull_labeled("stark").is_none());
+    }
+
+    #[test]
+    fn should_bind_challenges_to_public_inputs() {
+        let mut a = Channel::new();
+        let mut b = Channel::new();
+        a.absorb_public(&[1, 2, 3]);
+        b.absorb_public(&[1, 2, 4]);
+        for x in 0..3 {
+            a.push_single(&u128_to_bytes(&x));
+            b.push_single(&u128_to_bytes(&x));
+        }
+        assert_ne!(a.prover_hash(), b.prover_hash());
+
+        let mut verifier: Channel = Channel::deserialize(&a.serialize());
+        verifier.absorb_public(&[1, 2, 3]);
+        verifier.pull();
+        a.pull();
+        assert_eq!(verifier.verifier_hash(), a.verifier_hash());
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_fail_to_absorb_after_sampling() {
+        let mut c = Channel::new();
+        c.push_single(&u128_to_bytes(&1));
+        c.prover_hash();
+        c.absorb_public(&[1]);
+    }
+
+    #[test]
+    fn should_use_prover_and_verifier_channels() {
+        let mut prover = Channel::new_prover();
+        prover.push_single(&u128_to_bytes(&1));
+        let mut verifier = Channel::new_verifier(&prover.serialize());
+        verifier.pull();
+        assert_eq!(verifier.verifier_hash(), prover.prover_hash());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "pull on a prover channel")]
+    fn should_fail_to_pull_on_prover() {
+        let mut c = Channel::new_prover();
+        c.push_single(&u128_to_bytes(&1));
+        c.pull();
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "push on a verifier channel")]
+    fn should_fail_to_push_on_verifier() {
+        let mut c = Channel::new_verifier(&Channel::new_prover().serialize());
+        c.push_single(&u128_to_bytes(&1));
+    }
+
+    #[test]
+    fn should_measure_and_reset() {
+        let mut c = Channel::new();
+        assert!(c.is_empty());
+        c.push_single(&u128_to_bytes(&1));
+        c.push(&[u128_to_bytes(&2), u128_to_bytes(&3)]);
+        c.push_single_labeled("label", &u128_to_bytes(&4));
+        assert_eq!(c.len(), 3);
+        assert_eq!(c.byte_len(), 4 * 32);
+
+        c.prover_hash();
+        c.pull();
+        c.reset();
+        assert!(c.is_empty());
+        assert_eq!(c.byte_len(), 0);
+
+        // public inputs can be absorbed again after a reset
+        let mut fresh = Channel::new();
+        fresh.absorb_public(&[1]);
+        c.absorb_public(&[1]);
+        assert_eq!(c.prover_hash(), fresh.prover_hash());
+        c.push_single(&u128_to_bytes(&1));
+        assert_eq!(c.pull().data, u128_to_bytes(&1).to_vec());
+    }
 }
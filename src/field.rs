@@ -1,10 +1,50 @@
-use crate::field_element::{FieldElement};
+use crate::field_element::{bytes_le_to_dec, dec_to_bytes_le, FieldElement};
 use rand::Rng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::RwLock;
 
-#[derive(Serialize, Debug)]
+// bits per window in fixed base exponentiation tables
+const BASE_TABLE_WINDOW_BITS: u32 = 4;
+// trial division bound used when factoring p - 1
+const TRIAL_DIVISION_BOUND: u32 = 1 << 16;
+// largest candidate tried when searching for a generator
+const GENERATOR_SEARCH_BOUND: u32 = 1 << 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldError {
+    // zero has no multiplicative inverse
+    DivideByZero,
+    // p - 1 has a factor too large for trial division
+    FactorizationFailed,
+    // no primitive root was found below the search bound
+    NoGenerator,
+    // subgroup size does not divide p - 1
+    SubgroupNotDivisor,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldError::DivideByZero => write!(f, "divide by zero"),
+            FieldError::FactorizationFailed => write!(f, "unable to factor p - 1"),
+            FieldError::NoGenerator => write!(f, "no generator found for field"),
+            FieldError::SubgroupNotDivisor => write!(f, "subgroup is not a divisor of field"),
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+// powers of a fixed base, row i holds base^(j * 2^(w * i))
+// for each window digit j
+#[derive(Debug)]
+pub struct BaseTable<T: FieldElement> {
+    rows: Vec<Vec<T>>,
+}
+
+#[derive(Debug)]
 pub struct Field<T: FieldElement> {
     p: T::ParamsType,
     g: T,
@@ -31,12 +71,16 @@ impl<T: FieldElement> Field<T> {
         }
 
         // build a cache of generators and inverted generators
+        // every generator is a power of g so share a table
+        let p_minus_one = f.neg(&f.one());
+        let table = f.precompute_base(&f.g, u32::try_from(p_minus_one.bits()).unwrap());
         let mut start = 1;
         let mut sizes: Vec<u32> = Vec::new();
         let mut generators = Vec::new();
-        for _ in 0..31 {
+        for _ in 0..std::cmp::min(31, f.two_adicity()) {
             start *= 2;
-            generators.push(f.generator(T::from_u32(start, &f.p)));
+            let exp = f.div(&p_minus_one, &T::from_u32(start, &f.p));
+            generators.push(f.exp_with_table(&table, &exp));
             sizes.push(start);
         }
         let generators_inv = f.inv_batch(&generators);
@@ -47,6 +91,38 @@ impl<T: FieldElement> Field<T> {
         f
     }
 
+    // build a field from only a prime by searching for the
+    // smallest primitive root
+    pub fn from_prime(p: &T::ParamsType) -> Result<Field<T>, FieldError> {
+        let one = T::one(p);
+        let p_minus_one = T::zero(p).sub(&one);
+        let factors = prime_factors(&p_minus_one, p)?;
+        // g is a generator if g^((p-1)/q) != 1 for each prime factor q
+        let exps: Vec<T> = factors.iter().map(|q| p_minus_one.div(q)).collect();
+        for candidate in 2..GENERATOR_SEARCH_BOUND {
+            let g = T::from_u32(candidate, p);
+            if g == T::zero(p) {
+                break;
+            }
+            if exps.iter().all(|e| g.modpow(e) != one) {
+                return Ok(Field::new(g));
+            }
+        }
+        Err(FieldError::NoGenerator)
+    }
+
+    // whether g generates the whole multiplicative group of p,
+    // g^((p-1)/q) != 1 for every prime factor q of p - 1
+    pub fn is_generator(g: &T, p: &T::ParamsType) -> Result<bool, FieldError> {
+        let one = T::one(p);
+        if g == &T::zero(p) {
+            return Ok(false);
+        }
+        let p_minus_one = T::zero(p).sub(&one);
+        let factors = prime_factors(&p_minus_one, p)?;
+        Ok(factors.iter().all(|q| g.modpow(&p_minus_one.div(q)) != one))
+    }
+
     pub fn bigint(&self, val: i32) -> T {
         T::from_i32(val, self.p())
     }
@@ -79,10 +155,22 @@ impl<T: FieldElement> Field<T> {
         v1.add(v2)
     }
 
+    pub fn sum(&self, values: &[T]) -> T {
+        values.iter().fold(self.zero(), |acc, v| acc.add(v))
+    }
+
+    pub fn product(&self, values: &[T]) -> T {
+        values.iter().fold(self.one(), |acc, v| acc.mul(v))
+    }
+
     pub fn mul(&self, v1: &T, v2: &T) -> T {
         v1.mul(v2)
     }
 
+    pub fn square(&self, v: &T) -> T {
+        v.square()
+    }
+
     pub fn sub(&self, v1: &T, v2: &T) -> T {
         v1.sub(v2)
     }
@@ -99,11 +187,53 @@ impl<T: FieldElement> Field<T> {
     pub fn exp(&self, v: &T, e: &T) -> T {
         if e == &self.one() {
             v.clone()
+        } else if e == &self.two() {
+            v.square()
         } else {
             v.modpow(e)
         }
     }
 
+    // precompute powers of base for exponents up to max_bits
+    pub fn precompute_base(&self, base: &T, max_bits: u32) -> BaseTable<T> {
+        let window_size = 1 << BASE_TABLE_WINDOW_BITS;
+        let row_count = max_bits.div_ceil(BASE_TABLE_WINDOW_BITS);
+        let mut rows = Vec::new();
+        let mut row_base = base.clone();
+        for _ in 0..row_count {
+            let mut row = vec![self.one()];
+            for j in 1..window_size {
+                row.push(self.mul(&row[j - 1], &row_base));
+            }
+            // base^(window_size^(i+1))
+            row_base = self.mul(&row[window_size - 1], &row_base);
+            rows.push(row);
+        }
+        BaseTable { rows }
+    }
+
+    // exponentiate the base of a table, one multiplication per window
+    pub fn exp_with_table(&self, table: &BaseTable<T>, e: &T) -> T {
+        let windows_per_byte = 8 / BASE_TABLE_WINDOW_BITS;
+        let mask = (1_u8 << BASE_TABLE_WINDOW_BITS) - 1;
+        let mut out = self.one();
+        for (i, b) in e.to_bytes_le().iter().enumerate() {
+            for k in 0..windows_per_byte {
+                let digit = usize::from((b >> (k * BASE_TABLE_WINDOW_BITS)) & mask);
+                if digit == 0 {
+                    continue;
+                }
+                let row =
+                    i * usize::try_from(windows_per_byte).unwrap() + usize::try_from(k).unwrap();
+                if row >= table.rows.len() {
+                    panic!("exponent is larger than the precomputed table");
+                }
+                out = self.mul(&out, &table.rows[row][digit]);
+            }
+        }
+        out
+    }
+
     pub fn generator_cache(&self, size: &u32) -> (T, T) {
         if let Some(v) = self.generator_cache.get(size) {
             return v.clone();
@@ -114,39 +244,205 @@ impl<T: FieldElement> Field<T> {
     }
 
     pub fn generator(&self, size: T) -> T {
-        let transformed_p = T::from_params(self.p());
-        let numer = &transformed_p.sub(&T::one(self.p()));
-        let exp = numer.div(&size);
-        if exp.mul(&size) != *numer {
-            panic!("subgroup is not a divisor of field");
+        match self.try_generator(&size) {
+            Ok(g) => g,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    // generator of the subgroup with `size` elements
+    pub fn try_generator(&self, size: &T) -> Result<T, FieldError> {
+        if size == &self.zero() {
+            return Err(FieldError::SubgroupNotDivisor);
+        }
+        // if size divides p - 1 this is the integer quotient,
+        // otherwise size * exp = p - 1 + k * p for some 0 < k < size
+        // and h^size = g^k, which isn't 1 for a generator g
+        let numer = self.neg(&self.one());
+        let exp = self.div(&numer, size);
+        let h = self.exp(&self.g, &exp);
+        if self.exp(&h, size) != self.one() {
+            return Err(FieldError::SubgroupNotDivisor);
         }
-        self.exp(&self.g, &exp)
+        Ok(h)
+    }
+
+    // largest k such that 2^k divides p - 1
+    pub fn two_adicity(&self) -> u32 {
+        let p_minus_one = self.neg(&self.one());
+        let mut k = 0;
+        for b in p_minus_one.to_bytes_le() {
+            if b != 0 {
+                return k + b.trailing_zeros();
+            }
+            k += 8;
+        }
+        k
+    }
+
+    // size of the largest power of two subgroup
+    pub fn max_pow2_subgroup_size(&self) -> T {
+        let mut size = self.one();
+        for _ in 0..self.two_adicity() {
+            size = self.mul(&size, &self.two());
+        }
+        size
+    }
+
+    // smallest k such that v^k = 1
+    pub fn order(&self, v: &T) -> T {
+        if v == &self.zero() {
+            panic!("zero has no multiplicative order");
+        }
+        let one = self.one();
+        let mut order = self.neg(&one);
+        let factors = prime_factors(&order, self.p()).expect("unable to factor p - 1");
+        for q in factors {
+            let q_u32 = q.to_u32();
+            while rem_u32(&order, q_u32) == 0 {
+                let reduced = self.div(&order, &q);
+                if self.exp(v, &reduced) != one {
+                    break;
+                }
+                order = reduced;
+            }
+        }
+        order
     }
 
     pub fn inv(&self, v: &T) -> T {
-        v.inv()
+        self.try_inv(v).unwrap()
+    }
+
+    pub fn try_inv(&self, v: &T) -> Result<T, FieldError> {
+        if v == &self.zero() {
+            return Err(FieldError::DivideByZero);
+        }
+        Ok(v.inv())
+    }
+
+    // zero has no inverse, return None instead of
+    // a meaningless value
+    pub fn inv_checked(&self, v: &T) -> Option<T> {
+        self.try_inv(v).ok()
+    }
+
+    pub fn inv_batch(&self, values: &[T]) -> Vec<T> {
+        self.batch_inv(values)
     }
 
-    pub fn inv_batch(&self, values: &Vec<T>) -> Vec<T> {
-        let mut last = T::one(self.p());
-        let mut result = Vec::new();
+    // invert many values at once using montgomery's trick
+    // the product of all values is inverted a single time
+    // and each inverse is recovered with multiplications
+    //
+    // zero values have no inverse, they are skipped and
+    // zero is returned in their slot
+    pub fn batch_inv(&self, values: &[T]) -> Vec<T> {
+        let zero = self.zero();
+        let mut last = self.one();
+        let mut result = Vec::with_capacity(values.len());
         for v in values {
             result.push(last.clone());
-            last = self.mul(&last, v);
+            if v != &zero {
+                last = self.mul(&last, v);
+            }
         }
         last = self.inv(&last);
         for i in (0..values.len()).rev() {
+            if values[i] == zero {
+                result[i] = zero.clone();
+                continue;
+            }
             result[i] = self.mul(&result[i], &last);
             last = self.mul(&values[i], &last);
         }
         result
     }
 
+    // euler's criterion, v^((p-1)/2)
+    // returns 0 for zero, 1 for quadratic residues
+    // and -1 for non-residues
+    pub fn legendre(&self, v: &T) -> i8 {
+        if v == &self.zero() {
+            return 0;
+        }
+        let half = self.div(&self.neg(&self.one()), &self.two());
+        if self.exp(v, &half) == self.one() {
+            1
+        } else {
+            -1
+        }
+    }
+
+    pub fn is_quadratic_residue(&self, v: &T) -> bool {
+        self.legendre(v) == 1
+    }
+
+    // square root using tonelli-shanks
+    // https://en.wikipedia.org/wiki/Tonelli%E2%80%93Shanks_algorithm
+    //
+    // returns None if v is not a quadratic residue, otherwise
+    // one of the two roots
+    pub fn sqrt(&self, v: &T) -> Option<T> {
+        let zero = self.zero();
+        let one = self.one();
+        if v == &zero {
+            return Some(zero);
+        }
+        if !self.is_quadratic_residue(v) {
+            return None;
+        }
+        let p_minus_one = self.neg(&one);
+        let two_inv = self.inv(&self.two());
+        // write p - 1 as q * 2^s with q odd
+        let mut q = p_minus_one.clone();
+        let mut s = 0;
+        while q.to_bytes_le()[0] & 1 == 0 {
+            q = self.mul(&q, &two_inv);
+            s += 1;
+        }
+        // find any non-residue
+        let mut z = self.two();
+        while self.legendre(&z) != -1 {
+            z = self.add(&z, &one);
+        }
+        let mut m = s;
+        let mut c = self.exp(&z, &q);
+        let mut t = self.exp(v, &q);
+        let mut r = self.exp(v, &self.mul(&self.add(&q, &one), &two_inv));
+        while t != one {
+            // find the least i such that t^(2^i) = 1
+            let mut i = 0;
+            let mut t_pow = t.clone();
+            while t_pow != one {
+                t_pow = self.square(&t_pow);
+                i += 1;
+            }
+            let mut b = c.clone();
+            for _ in 0..(m - i - 1) {
+                b = self.square(&b);
+            }
+            m = i;
+            c = self.square(&b);
+            t = self.mul(&t, &c);
+            r = self.mul(&r, &b);
+        }
+        Some(r)
+    }
+
+    // non-deterministic, uses the thread rng
     pub fn random(&self) -> T {
         let mut rng = rand::thread_rng();
         self.bigint(rng.gen())
     }
 
+    // deterministic element derived from the hash of a seed
+    pub fn random_seeded(&self, seed: &[u8]) -> T {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(seed);
+        T::from_bytes_le(hasher.finalize().as_bytes(), self.p())
+    }
+
     pub fn sample(&self, input: T) -> T {
         // input.modd(self.p())
         input
@@ -215,6 +511,109 @@ impl<T: FieldElement> Field<T> {
         cache.insert((generator.clone(), size), domain);
         d
     }
+
+    // [1, v, v^2, ..., v^(len-1)], not cached
+    pub fn power_series(&self, v: &T, len: usize) -> Vec<T> {
+        let mut out = Vec::with_capacity(len);
+        if len == 0 {
+            return out;
+        }
+        out.push(self.one());
+        for i in 1..len {
+            out.push(self.mul(&out[i - 1], v));
+        }
+        out
+    }
+}
+
+// fields are equal if they share a prime and generator
+impl<T: FieldElement> PartialEq for Field<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.p == other.p && self.g == other.g
+    }
+}
+
+// a field is stored as the prime and generator, caches
+// are rebuilt when deserializing
+#[derive(Serialize, Deserialize)]
+struct SerializedField<P> {
+    p: P,
+    g: String,
+}
+
+impl<T: FieldElement> Serialize for Field<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        SerializedField {
+            p: &self.p,
+            g: bytes_le_to_dec(&self.g.to_bytes_le()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: FieldElement> Deserialize<'de> for Field<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let f = SerializedField::<T::ParamsType>::deserialize(deserializer)?;
+        let mut g_bytes = dec_to_bytes_le(&f.g)
+            .ok_or_else(|| serde::de::Error::custom("invalid decimal generator"))?;
+        if g_bytes.len() > 32 {
+            return Err(serde::de::Error::custom("generator is too large"));
+        }
+        g_bytes.resize(32, 0);
+        // from_bytes_le reduces and truncates, so a generator that
+        // doesn't come back out unchanged isn't below the prime
+        let g = T::from_bytes_le(&g_bytes, &f.p);
+        if g.to_bytes_le_sized()[..] != g_bytes[..] {
+            return Err(serde::de::Error::custom("generator is not below the prime"));
+        }
+        if g == T::zero(&f.p) {
+            return Err(serde::de::Error::custom("generator is zero"));
+        }
+        Ok(Field::new(g))
+    }
+}
+
+// remainder of the integer representation of v divided by m
+fn rem_u32<T: FieldElement>(v: &T, m: u32) -> u32 {
+    let mut r: u64 = 0;
+    for b in v.to_bytes_le().iter().rev() {
+        r = ((r << 8) + u64::from(*b)) % u64::from(m);
+    }
+    u32::try_from(r).unwrap()
+}
+
+// distinct prime factors of v using trial division
+fn prime_factors<T: FieldElement>(v: &T, p: &T::ParamsType) -> Result<Vec<T>, FieldError> {
+    let one = T::one(p);
+    let mut remaining = v.clone();
+    let mut factors = Vec::new();
+    let mut q = 2;
+    while q < TRIAL_DIVISION_BOUND && remaining != one {
+        if rem_u32(&remaining, q) == 0 {
+            let factor = T::from_u32(q, p);
+            // exact division, the quotient is the integer quotient
+            while rem_u32(&remaining, q) == 0 {
+                remaining = remaining.div(&factor);
+            }
+            factors.push(factor);
+        }
+        q += if q == 2 { 1 } else { 2 };
+    }
+    if remaining != one {
+        // no factors below the bound, so anything smaller than
+        // the bound squared must be prime
+        if remaining.bits() > 2 * u64::from(TRIAL_DIVISION_BOUND.ilog2()) {
+            return Err(FieldError::FactorizationFailed);
+        }
+        factors.push(remaining);
+    }
+    Ok(factors)
 }
 
 #[cfg(test)]
@@ -279,6 +678,19 @@ mod tests {
         assert_eq!(f.add(&x, &y), f.bigint(29));
     }
 
+    #[test]
+    fn should_negate_elements() {
+        let f = test_field();
+
+        // includes zero and values larger than p
+        for v in [0, 1, 50, 100, 101, 150, 1000] {
+            let x = f.biguint(v);
+            assert_eq!(f.add(&x, &f.neg(&x)), f.zero());
+        }
+        assert_eq!(f.neg(&f.zero()), f.zero());
+        assert_eq!(f.neg(&f.bigint(1)), f.bigint(-1));
+    }
+
     #[test]
     fn should_mul_two_elements() {
         let f = test_field();
@@ -324,4 +736,427 @@ mod tests {
             assert_eq!(f.mul(&inv, &v), f.bigint(1));
         }
     }
+
+    #[test]
+    fn should_batch_invert() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Field::new(g);
+
+        let values: Vec<CryptoBigIntElement> = (1..100).map(|i| f.biguint(i * 7919)).collect();
+        let inverted = f.batch_inv(&values);
+        assert_eq!(inverted.len(), values.len());
+        for i in 0..values.len() {
+            assert_eq!(inverted[i], f.inv(&values[i]));
+        }
+    }
+
+    #[test]
+    fn should_batch_invert_with_zero() {
+        let f = test_field();
+
+        let values = vec![f.bigint(3), f.zero(), f.bigint(40), f.zero()];
+        let inverted = f.batch_inv(&values);
+        assert_eq!(inverted[0], f.inv(&values[0]));
+        assert_eq!(inverted[1], f.zero());
+        assert_eq!(inverted[2], f.inv(&values[2]));
+        assert_eq!(inverted[3], f.zero());
+    }
+
+    #[test]
+    fn should_div_elements() {
+        let f = test_field();
+
+        for a in [0, 1, 17, 99, 150] {
+            for b in [1, 2, 33, 100, 250] {
+                let x = f.biguint(a);
+                let y = f.biguint(b);
+                assert_eq!(f.mul(&f.div(&x, &y), &y), f.biguint(a));
+            }
+        }
+    }
+
+    #[test]
+    fn should_not_invert_zero() {
+        let f = test_field();
+
+        assert_eq!(f.inv_checked(&f.zero()), None);
+        assert_eq!(f.inv_checked(&f.biguint(101)), None);
+        let v = f.biguint(7);
+        assert_eq!(f.inv_checked(&v), Some(f.inv(&v)));
+    }
+
+    #[test]
+    fn should_sqrt_elements() {
+        let f = test_field();
+
+        for i in 0..101 {
+            let v = f.biguint(i);
+            let square = f.mul(&v, &v);
+            let root = f.sqrt(&square).unwrap();
+            assert_eq!(f.mul(&root, &root), square);
+        }
+
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(1_u128 + 407_u128 * 2_u128.pow(119))));
+        let g = CryptoBigIntElement::from_u32(0, &p);
+        let f = Field::new(g);
+        for _ in 0..10 {
+            let v = f.random();
+            let square = f.mul(&v, &v);
+            let root = f.sqrt(&square).unwrap();
+            assert_eq!(f.mul(&root, &root), square);
+        }
+    }
+
+    #[test]
+    fn should_not_sqrt_non_residue() {
+        let f = test_field();
+
+        // 101 = 5 mod 8 so 2 is a non-residue
+        for i in [2, 3, 8, 10] {
+            assert_eq!(f.sqrt(&f.biguint(i)), None);
+        }
+    }
+
+    #[test]
+    fn should_compute_legendre_symbol() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(11_u128)));
+        let g = CryptoBigIntElement::from_u32(2, &p);
+        let f = Field::new(g);
+
+        // squares mod 11 are 1, 3, 4, 5, 9
+        let residues = [1, 3, 4, 5, 9];
+        for i in 1..11 {
+            let v = f.biguint(i);
+            if residues.contains(&i) {
+                assert_eq!(f.legendre(&v), 1);
+                assert!(f.is_quadratic_residue(&v));
+            } else {
+                assert_eq!(f.legendre(&v), -1);
+                assert!(!f.is_quadratic_residue(&v));
+            }
+        }
+        assert_eq!(f.legendre(&f.zero()), 0);
+        assert!(!f.is_quadratic_residue(&f.zero()));
+    }
+
+    #[test]
+    fn should_find_generator_from_prime() {
+        for prime in [101_u128, 3221225473_u128, 1_u128 + 407_u128 * 2_u128.pow(119)] {
+            let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(prime)));
+            let f = Field::<CryptoBigIntElement>::from_prime(&p).unwrap();
+
+            let p_minus_one = f.neg(&f.one());
+            let factors = prime_factors(&p_minus_one, f.p()).unwrap();
+            assert!(!factors.is_empty());
+            for q in factors {
+                assert_ne!(f.exp(f.g(), &f.div(&p_minus_one, &q)), f.one());
+            }
+        }
+    }
+
+    #[test]
+    fn should_check_generator() {
+        let f = test_field();
+        // 2 generates the units of 101, 4 is a square
+        assert_eq!(Field::is_generator(&f.biguint(2), f.p()), Ok(true));
+        assert_eq!(Field::is_generator(&f.biguint(4), f.p()), Ok(false));
+        assert_eq!(Field::is_generator(&f.one(), f.p()), Ok(false));
+        assert_eq!(Field::is_generator(&f.zero(), f.p()), Ok(false));
+    }
+
+    #[test]
+    fn should_factor_p_minus_one() {
+        let f = test_field();
+
+        // 100 = 2^2 * 5^2
+        let factors = prime_factors(&f.biguint(100), f.p()).unwrap();
+        assert_eq!(factors, vec![f.biguint(2), f.biguint(5)]);
+    }
+
+    #[test]
+    fn should_cache_domain() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(1_u128 + 407_u128 * 2_u128.pow(119))));
+        let g = CryptoBigIntElement::from_u32(3, &p);
+        let f = Field::new(g);
+
+        let size = 8192;
+        let generator = f.generator(f.biguint(size));
+        let domain = f.domain(&generator, size);
+        assert!(f
+            .group_cache
+            .read()
+            .unwrap()
+            .contains_key(&(generator.clone(), size)));
+        assert_eq!(domain, f.domain(&generator, size));
+
+        let offset = f.biguint(7);
+        let coset = f.coset(size, &offset);
+        assert!(f
+            .coset_cache
+            .read()
+            .unwrap()
+            .contains_key(&(size, offset.clone())));
+        assert_eq!(coset, f.coset(size, &offset));
+        assert_eq!(coset[1], f.mul(&domain[1], &offset));
+    }
+
+    #[test]
+    fn should_reduce_multiples_of_p() {
+        let f = test_field();
+
+        for v in [101_u32, 202_u32] {
+            let mut bytes = v.to_le_bytes().to_vec();
+            bytes.resize(32, 0);
+            assert_eq!(CryptoBigIntElement::from_bytes_le(&bytes, f.p()), f.zero());
+            assert_eq!(f.biguint(v), f.zero());
+        }
+        assert_eq!(CryptoBigIntElement::from_params(f.p()), f.zero());
+    }
+
+    #[test]
+    fn should_square_elements() {
+        let f = test_field();
+
+        for i in -150..250 {
+            let x = f.bigint(i);
+            assert_eq!(f.square(&x), f.mul(&x, &x));
+            assert_eq!(f.exp(&x, &f.two()), f.mul(&x, &x));
+        }
+    }
+
+    #[test]
+    fn should_get_order() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Field::new(g);
+
+        assert_eq!(f.order(&f.one()), f.one());
+        assert_eq!(f.order(&f.bigint(-1)), f.two());
+        assert_eq!(f.order(f.g()), f.bigint(-1));
+        for i in 1..20 {
+            let size = f.biguint(u32::pow(2, i));
+            let g = f.generator(size.clone());
+            assert_eq!(f.order(&g), size);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_fail_to_get_order_of_zero() {
+        let f = test_field();
+
+        f.order(&f.zero());
+    }
+
+    #[test]
+    fn should_serialize_field() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Field::new(g);
+
+        let json = serde_json::to_string(&f).unwrap();
+        assert_eq!(json, "{\"p\":\"3221225473\",\"g\":\"5\"}");
+        let f2: Field<CryptoBigIntElement> = serde_json::from_str(&json).unwrap();
+        assert_eq!(f2.g(), f.g());
+        let x = f.biguint(2819381);
+        let y = f.biguint(1289);
+        let x2 = f2.biguint(2819381);
+        let y2 = f2.biguint(1289);
+        assert_eq!(f.add(&x, &y), f2.add(&x2, &y2));
+        assert_eq!(f.mul(&x, &y), f2.mul(&x2, &y2));
+
+        let large = Field::new(crate::field_element::G);
+        let json = serde_json::to_string(&large).unwrap();
+        let large2: Field<CryptoBigIntElement> = serde_json::from_str(&json).unwrap();
+        assert_eq!(large.g(), large2.g());
+        assert_eq!(serde_json::to_string(&large2).unwrap(), json);
+    }
+
+    #[test]
+    fn should_fail_to_deserialize_field() {
+        let even = "{\"p\":\"3221225472\",\"g\":\"5\"}";
+        assert!(serde_json::from_str::<Field<CryptoBigIntElement>>(even).is_err());
+        let invalid = "{\"p\":\"32x21\",\"g\":\"5\"}";
+        assert!(serde_json::from_str::<Field<CryptoBigIntElement>>(invalid).is_err());
+        // p + 5 would reduce to 5
+        let unreduced = "{\"p\":\"3221225473\",\"g\":\"3221225478\"}";
+        assert!(serde_json::from_str::<Field<CryptoBigIntElement>>(unreduced).is_err());
+        let equal = "{\"p\":\"3221225473\",\"g\":\"3221225473\"}";
+        assert!(serde_json::from_str::<Field<CryptoBigIntElement>>(equal).is_err());
+        // 2^128 + 5 would be truncated to 5
+        let wide = "{\"p\":\"3221225473\",\"g\":\"340282366920938463463374607431768211461\"}";
+        assert!(serde_json::from_str::<Field<CryptoBigIntElement>>(wide).is_err());
+        let zero = "{\"p\":\"3221225473\",\"g\":\"0\"}";
+        assert!(serde_json::from_str::<Field<CryptoBigIntElement>>(zero).is_err());
+    }
+
+    #[test]
+    fn should_compare_fields() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let f1 = Field::new(CryptoBigIntElement::from_u32(5, &p));
+        let f2 = Field::new(CryptoBigIntElement::from_u32(5, &p));
+        let f3 = Field::new(CryptoBigIntElement::from_u32(10, &p));
+        assert!(f1 == f2);
+        assert!(f1 != f3);
+
+        let other_p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
+        let f4 = Field::new(CryptoBigIntElement::from_u32(5, &other_p));
+        assert!(f1 != f4);
+    }
+
+    #[test]
+    fn should_get_two_adicity() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(1_u128 + 407_u128 * 2_u128.pow(119))));
+        let f = Field::new(CryptoBigIntElement::from_u32(3, &p));
+        assert_eq!(f.two_adicity(), 119);
+        let mut bytes = 2_u128.pow(119).to_le_bytes().to_vec();
+        bytes.resize(32, 0);
+        assert_eq!(
+            f.max_pow2_subgroup_size(),
+            CryptoBigIntElement::from_bytes_le(&bytes, f.p())
+        );
+
+        // 100 = 2^2 * 25
+        let f = test_field();
+        assert_eq!(f.two_adicity(), 2);
+        assert_eq!(f.max_pow2_subgroup_size(), f.biguint(4));
+
+        // 3221225472 = 3 * 2^30
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let f = Field::new(CryptoBigIntElement::from_u32(5, &p));
+        assert_eq!(f.two_adicity(), 30);
+        assert_eq!(f.max_pow2_subgroup_size(), f.biguint(2_u32.pow(30)));
+    }
+
+    #[test]
+    fn should_get_seeded_random() {
+        let f = test_field();
+
+        let a = f.random_seeded(b"seed");
+        let b = f.random_seeded(b"seed");
+        assert_eq!(a, b);
+
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let f = Field::new(CryptoBigIntElement::from_u32(5, &p));
+        assert_eq!(f.random_seeded(&[1, 2, 3]), f.random_seeded(&[1, 2, 3]));
+        assert_ne!(f.random_seeded(&[1, 2, 3]), f.random_seeded(&[1, 2, 4]));
+    }
+
+    #[test]
+    fn should_exp_with_table() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(1_u128 + 407_u128 * 2_u128.pow(119))));
+        let f = Field::new(CryptoBigIntElement::from_u32(3, &p));
+
+        let base = f.biguint(3);
+        let table = f.precompute_base(&base, 128);
+        for _ in 0..100 {
+            let e = f.random();
+            assert_eq!(f.exp_with_table(&table, &e), f.exp(&base, &e));
+        }
+        let e = f.neg(&f.one());
+        assert_eq!(f.exp_with_table(&table, &e), f.exp(&base, &e));
+        assert_eq!(f.exp_with_table(&table, &f.zero()), f.one());
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_fail_to_exp_with_small_table() {
+        let f = test_field();
+
+        let table = f.precompute_base(&f.biguint(3), 4);
+        f.exp_with_table(&table, &f.biguint(99));
+    }
+
+    #[test]
+    fn should_sum_and_product() {
+        let f = test_field();
+
+        let values = vec![f.biguint(40), f.biguint(90), f.biguint(3)];
+        // 133 mod 101
+        assert_eq!(f.sum(&values), f.biguint(32));
+        // 10800 mod 101
+        assert_eq!(f.product(&values), f.biguint(94));
+        assert_eq!(f.sum(&[]), f.zero());
+        assert_eq!(f.product(&[]), f.one());
+    }
+
+    #[test]
+    fn should_fail_to_try_inv_zero() {
+        let f = test_field();
+
+        assert_eq!(f.try_inv(&f.zero()), Err(FieldError::DivideByZero));
+        let v = f.biguint(12);
+        assert_eq!(f.mul(&f.try_inv(&v).unwrap(), &v), f.one());
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_fail_to_inv_zero() {
+        let f = test_field();
+
+        f.inv(&f.zero());
+    }
+
+    #[test]
+    fn should_try_generator() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let f = Field::new(CryptoBigIntElement::from_u32(5, &p));
+
+        let g = f.try_generator(&f.biguint(1024)).unwrap();
+        assert_eq!(g, f.generator(f.biguint(1024)));
+        assert_eq!(f.order(&g), f.biguint(1024));
+        assert_eq!(
+            f.try_generator(&f.biguint(3 * 1024)),
+            Ok(f.generator(f.biguint(3 * 1024)))
+        );
+        assert_eq!(
+            f.try_generator(&f.biguint(7)),
+            Err(FieldError::SubgroupNotDivisor)
+        );
+        assert_eq!(
+            f.try_generator(&f.zero()),
+            Err(FieldError::SubgroupNotDivisor)
+        );
+        // the whole group
+        let p_minus_one = f.neg(&f.one());
+        assert_eq!(f.try_generator(&p_minus_one), Ok(f.g().clone()));
+
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
+        let f = Field::new(CryptoBigIntElement::from_u32(2, &p));
+        assert_eq!(
+            f.try_generator(&f.biguint(3)),
+            Err(FieldError::SubgroupNotDivisor)
+        );
+        assert_eq!(
+            f.order(&f.try_generator(&f.biguint(25)).unwrap()),
+            f.biguint(25)
+        );
+    }
+
+    #[test]
+    fn should_get_generator_above_32_bits() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(
+            1_u128 + 407_u128 * 2_u128.pow(119),
+        )));
+        let f = Field::<CryptoBigIntElement>::from_prime(&p).unwrap();
+
+        let size = f.exp(&f.two(), &f.biguint(40));
+        let g = f.generator(size.clone());
+        assert_eq!(f.order(&g), size);
+        let too_large = f.exp(&f.two(), &f.biguint(120));
+        assert_eq!(
+            f.try_generator(&too_large),
+            Err(FieldError::SubgroupNotDivisor)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_fail_to_get_generator() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let f = Field::new(CryptoBigIntElement::from_u32(5, &p));
+
+        f.generator(f.biguint(7));
+    }
 }
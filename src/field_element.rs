@@ -1,5 +1,6 @@
 use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
 use crypto_bigint::{Encoding, U256, U128, U64};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::hash::Hash;
 use std::fmt::Debug;
@@ -26,11 +27,14 @@ const POINTER_WIDTH: usize = 64;
 
 pub const LIMBS: usize = P_BITS / POINTER_WIDTH;
 
-pub trait FieldElement: Eq + PartialEq + Clone + PartialOrd + Hash + Debug {
-    type ParamsType: Serialize + Debug;
+// elements are shared between threads when the rayon
+// feature is enabled
+pub trait FieldElement: Eq + PartialEq + Clone + PartialOrd + Hash + Debug + Send + Sync {
+    type ParamsType: Serialize + DeserializeOwned + Debug + PartialEq + Send + Sync;
     fn add(&self, v: &Self) -> Self;
     fn sub(&self, v: &Self) -> Self;
     fn mul(&self, v: &Self) -> Self;
+    fn square(&self) -> Self;
     fn div(&self, v: &Self) -> Self;
     fn modpow(&self, e: &Self) -> Self;
     fn inv(&self) -> Self;
@@ -97,6 +101,10 @@ impl FieldElement for CryptoBigIntElement {
         CryptoBigIntElement(self.0 * v.0)
     }
 
+    fn square(&self) -> Self {
+        CryptoBigIntElement(self.0.square())
+    }
+
     fn div(&self, v: &Self) -> Self {
         CryptoBigIntElement(self.0 * v.0.invert().0)
     }
@@ -126,7 +134,7 @@ impl FieldElement for CryptoBigIntElement {
     }
 
     fn to_u32(&self) -> u32 {
-        u32::from_le_bytes(self.to_bytes_le_sized()[..LIMBS].try_into().unwrap())
+        u32::from_le_bytes(self.to_bytes_le_sized()[..4].try_into().unwrap())
     }
 
     fn from_bytes_le(v: &[u8], p: &ParamWrapper) -> Self {
@@ -175,7 +183,7 @@ impl FieldElement for CryptoBigIntElement {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ParamWrapper(pub DynResidueParams<LIMBS>);
 
 impl Serialize for ParamWrapper {
@@ -183,6 +191,66 @@ impl Serialize for ParamWrapper {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.0.modulus().to_string())
+        serializer.serialize_str(&bytes_le_to_dec(&self.0.modulus().to_le_bytes()))
+    }
+}
+
+impl<'de> Deserialize<'de> for ParamWrapper {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let mut bytes = dec_to_bytes_le(&s)
+            .ok_or_else(|| serde::de::Error::custom("invalid decimal modulus"))?;
+        if bytes.len() > P_BITS / 8 {
+            return Err(serde::de::Error::custom("modulus is too large"));
+        }
+        bytes.resize(P_BITS / 8, 0);
+        if bytes[0] & 1 == 0 {
+            return Err(serde::de::Error::custom("modulus must be odd"));
+        }
+        Ok(ParamWrapper(DynResidueParams::new(&UC::from_le_slice(&bytes))))
+    }
+}
+
+// convert a little endian unsigned integer to a decimal string
+pub fn bytes_le_to_dec(bytes: &[u8]) -> String {
+    let mut be: Vec<u8> = bytes.iter().rev().copied().collect();
+    let mut digits = Vec::new();
+    while be.iter().any(|b| *b != 0) {
+        // long division by 10
+        let mut rem: u32 = 0;
+        for b in be.iter_mut() {
+            let cur = (rem << 8) + u32::from(*b);
+            *b = u8::try_from(cur / 10).unwrap();
+            rem = cur % 10;
+        }
+        digits.push(char::from_digit(rem, 10).unwrap());
+    }
+    if digits.is_empty() {
+        return "0".to_string();
+    }
+    digits.iter().rev().collect()
+}
+
+// convert a decimal string to a little endian unsigned integer
+pub fn dec_to_bytes_le(s: &str) -> Option<Vec<u8>> {
+    if s.is_empty() {
+        return None;
+    }
+    let mut out: Vec<u8> = Vec::new();
+    for c in s.chars() {
+        let mut carry = c.to_digit(10)?;
+        for b in out.iter_mut() {
+            let cur = u32::from(*b) * 10 + carry;
+            *b = (cur & 0xff) as u8;
+            carry = cur >> 8;
+        }
+        while carry > 0 {
+            out.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
     }
+    Some(out)
 }
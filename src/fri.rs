@@ -1,17 +1,258 @@
-use crate::channel::Channel;
+use crate::channel::{Channel, ChannelHasher};
 use crate::field::Field;
 use crate::polynomial::Polynomial;
-use crate::tree::Tree;
+use crate::tree::{Tree, TreeError, TreeHasher};
 use crate::field_element::FieldElement;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 
+// labels separating the fri commitments from each other and
+// from the stark messages around them
+const ROOT_LABEL: &str = "fri_root";
+const LAST_CODEWORD_LABEL: &str = "fri_last_codeword";
+const BATCH_ROOT_LABEL: &str = "fri_batch_root";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FriError {
+    // the last codeword doesn't hash to the last committed root
+    LastCodewordRootMismatch,
+    // the last codeword isn't the length implied by the options
+    LastCodewordLength {
+        len: usize,
+        expected: usize,
+    },
+    // omega doesn't have the order implied by the last codeword
+    OmegaOrderIncorrect,
+    // the interpolant doesn't reproduce the last codeword
+    InterpolationMismatch,
+    // the last codeword is of too high a degree
+    DegreeTooHigh {
+        degree: usize,
+        max: usize,
+    },
+    // the opened points of a query are not colinear
+    ColinearityFailed {
+        round: usize,
+        query: usize,
+    },
+    // an opened point doesn't authenticate against its round root
+    InvalidPath {
+        round: usize,
+        error: TreeError,
+    },
+    // the grinding nonce doesn't have enough leading zero bits
+    InvalidProofOfWork,
+    // a batched codeword opening doesn't authenticate against its root
+    InvalidBatchPath {
+        codeword: usize,
+        error: TreeError,
+    },
+    // the batched openings don't combine to the proven codeword
+    BatchCombinationMismatch {
+        index: u32,
+    },
+    // a fri proof doesn't have the shape implied by the options
+    MalformedProof,
+    // more query indices were requested than distinct
+    // reduced indices exist
+    TooManyIndices {
+        count: u32,
+        reduced_size: u32,
+        max: u32,
+    },
+    // distinct indices weren't found within the attempt limit
+    SamplingExhausted {
+        attempts: u32,
+    },
+}
+
+impl fmt::Display for FriError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FriError::LastCodewordRootMismatch => write!(f, "last codeword root mismatch"),
+            FriError::LastCodewordLength { len, expected } => write!(
+                f,
+                "last codeword has length {} but {} was expected",
+                len, expected
+            ),
+            FriError::OmegaOrderIncorrect => write!(f, "omega order incorrect"),
+            FriError::InterpolationMismatch => write!(f, "interpolated polynomial is incorrect"),
+            FriError::DegreeTooHigh { degree, max } => write!(
+                f,
+                "last codeword has degree {} but at most {} is allowed",
+                degree, max
+            ),
+            FriError::ColinearityFailed { round, query } => write!(
+                f,
+                "colinearity test failed in round {} query {}",
+                round, query
+            ),
+            FriError::InvalidPath { round, error } => {
+                write!(f, "invalid path in round {}: {}", round, error)
+            }
+            FriError::InvalidProofOfWork => write!(f, "invalid proof of work"),
+            FriError::InvalidBatchPath { codeword, error } => {
+                write!(
+                    f,
+                    "invalid path for batched codeword {}: {}",
+                    codeword, error
+                )
+            }
+            FriError::BatchCombinationMismatch { index } => {
+                write!(f, "batched openings do not combine at index {}", index)
+            }
+            FriError::MalformedProof => write!(f, "malformed fri proof"),
+            FriError::TooManyIndices {
+                count,
+                reduced_size,
+                max,
+            } => write!(
+                f,
+                "cannot sample {} indices from a reduced domain of {}, at most {} can be sampled",
+                count, reduced_size, max
+            ),
+            FriError::SamplingExhausted { attempts } => {
+                write!(
+                    f,
+                    "could not sample distinct indices in {} attempts",
+                    attempts
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for FriError {}
+
+// the points a, b and c of one colinearity test and
+// their authentication paths
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FriQuery {
+    pub points: [[u8; 32]; 3],
+    pub paths: [Vec<[u8; 32]>; 3],
+}
+
+// everything a fri prover pushes to the channel, the
+// alphas and query indices are derived from it again
+// when verifying
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FriProof {
+    pub roots: Vec<[u8; 32]>,
+    pub last_codeword: Vec<[u8; 32]>,
+    pub nonce: Option<[u8; 32]>,
+    // the queries of every round but the last
+    pub rounds: Vec<Vec<FriQuery>>,
+}
+
+// lengths are u32 le, nodes and elements are 32 bytes
+pub(crate) fn write_words(out: &mut Vec<u8>, words: &[[u8; 32]]) {
+    out.extend_from_slice(&u32::try_from(words.len()).unwrap().to_le_bytes());
+    for word in words {
+        out.extend_from_slice(word);
+    }
+}
+
+pub(crate) fn read_u32(bytes: &[u8], pos: &mut usize) -> Option<u32> {
+    let v = bytes.get(*pos..(*pos + 4))?;
+    *pos += 4;
+    Some(u32::from_le_bytes(v.try_into().unwrap()))
+}
+
+pub(crate) fn read_word(bytes: &[u8], pos: &mut usize) -> Option<[u8; 32]> {
+    let v = bytes.get(*pos..(*pos + 32))?;
+    *pos += 32;
+    Some(v.try_into().unwrap())
+}
+
+pub(crate) fn read_words(bytes: &[u8], pos: &mut usize) -> Option<Vec<[u8; 32]>> {
+    let len = read_u32(bytes, pos)?;
+    (0..len).map(|_| read_word(bytes, pos)).collect()
+}
+
+impl FriProof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_words(&mut out, &self.roots);
+        write_words(&mut out, &self.last_codeword);
+        match &self.nonce {
+            Some(nonce) => {
+                out.push(1);
+                out.extend_from_slice(nonce);
+            }
+            None => out.push(0),
+        }
+        out.extend_from_slice(&u32::try_from(self.rounds.len()).unwrap().to_le_bytes());
+        for queries in &self.rounds {
+            out.extend_from_slice(&u32::try_from(queries.len()).unwrap().to_le_bytes());
+            for query in queries {
+                for point in &query.points {
+                    out.extend_from_slice(point);
+                }
+                for path in &query.paths {
+                    write_words(&mut out, path);
+                }
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<FriProof> {
+        let mut pos = 0;
+        let roots = read_words(bytes, &mut pos)?;
+        let last_codeword = read_words(bytes, &mut pos)?;
+        let has_nonce = *bytes.get(pos)?;
+        pos += 1;
+        let nonce = match has_nonce {
+            0 => None,
+            1 => Some(read_word(bytes, &mut pos)?),
+            _ => return None,
+        };
+        let mut rounds = Vec::new();
+        for _ in 0..read_u32(bytes, &mut pos)? {
+            let mut queries = Vec::new();
+            for _ in 0..read_u32(bytes, &mut pos)? {
+                let points = [
+                    read_word(bytes, &mut pos)?,
+                    read_word(bytes, &mut pos)?,
+                    read_word(bytes, &mut pos)?,
+                ];
+                let paths = [
+                    read_words(bytes, &mut pos)?,
+                    read_words(bytes, &mut pos)?,
+                    read_words(bytes, &mut pos)?,
+                ];
+                queries.push(FriQuery { points, paths });
+            }
+            rounds.push(queries);
+        }
+        if pos != bytes.len() {
+            return None;
+        }
+        Some(FriProof {
+            roots,
+            last_codeword,
+            nonce,
+            rounds,
+        })
+    }
+}
+
 pub struct FriOptions<T: FieldElement> {
     pub offset: T,
     pub omega: T,
     pub domain_len: u32,
     pub expansion_factor: u32,
     pub colinearity_test_count: u32,
+    // leading zero bits required of the grinding hash,
+    // 0 disables grinding
+    pub pow_bits: u32,
+    // stop folding before the last codeword drops below this
+    // degree, 0 folds as far as the test count allows
+    pub final_degree: u32,
 }
 
 pub struct Fri<T: FieldElement> {
@@ -21,21 +262,26 @@ pub struct Fri<T: FieldElement> {
     pub field: Rc<Field<T>>,
     pub expansion_factor: u32,
     pub colinearity_test_count: u32,
+    pub pow_bits: u32,
+    // hashes sample_indices may try before giving up
+    pub sample_attempts: u32,
     domain: Vec<T>,
     round_count: u32,
 }
 
 impl<T: FieldElement> Fri<T> {
     pub fn new(options: &FriOptions<T>, field: &Rc<Field<T>>) -> Fri<T> {
-        // calculate number of rounds
-        let mut codeword_len = options.domain_len;
-        let mut round_count = 0;
-        while codeword_len > options.expansion_factor
-            && 4 * options.colinearity_test_count < codeword_len
-        {
-            codeword_len /= 2;
-            round_count += 1;
-        }
+        debug_assert!(
+            &options.offset.get_params() == field.p() && &options.omega.get_params() == field.p(),
+            "fri options are not elements of the field"
+        );
+        let round_count = Self::fold_round_count(
+            options.domain_len,
+            options.expansion_factor,
+            options.colinearity_test_count,
+            options.final_degree,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
         Fri {
             offset: options.offset.clone(),
             omega: options.omega.clone(),
@@ -43,11 +289,49 @@ impl<T: FieldElement> Fri<T> {
             field: Rc::clone(field),
             expansion_factor: options.expansion_factor,
             colinearity_test_count: options.colinearity_test_count,
+            pow_bits: options.pow_bits,
+            sample_attempts: 64 * options.colinearity_test_count,
             domain: field.coset(options.domain_len, &options.offset),
             round_count,
         }
     }
 
+    // the number of rounds Fri::new would fold these options
+    // in, or why it would panic on them
+    pub fn fold_round_count(
+        domain_len: u32,
+        expansion_factor: u32,
+        colinearity_test_count: u32,
+        final_degree: u32,
+    ) -> Result<u32, &'static str> {
+        // folding halves the domain each round
+        if !domain_len.is_power_of_two() {
+            return Err("fri domain_len must be a power of two");
+        }
+        if !expansion_factor.is_power_of_two() {
+            return Err("fri expansion_factor must be a power of two");
+        }
+        if domain_len <= expansion_factor {
+            return Err("fri domain_len must be larger than expansion_factor");
+        }
+        // calculate number of rounds, prove and verify need at
+        // least two however large final_degree is
+        let final_len = final_degree.saturating_mul(expansion_factor);
+        let mut codeword_len = domain_len;
+        let mut round_count = 0;
+        while codeword_len > expansion_factor
+            && colinearity_test_count.saturating_mul(4) < codeword_len
+            && (round_count < 2 || codeword_len > final_len)
+        {
+            codeword_len /= 2;
+            round_count += 1;
+        }
+        if round_count < 2 {
+            return Err("fri domain_len is too small to fold twice with this expansion_factor and colinearity_test_count");
+        }
+        Ok(round_count)
+    }
+
     pub fn domain(&self) -> &Vec<T> {
         &self.domain
     }
@@ -56,29 +340,61 @@ impl<T: FieldElement> Fri<T> {
         self.round_count
     }
 
-    pub fn prove(&self, codeword: &Vec<T>, channel: &mut Channel) -> Vec<u32> {
+    fn last_codeword_len(&self) -> usize {
+        usize::try_from(self.domain_len >> (self.round_count() - 1)).unwrap()
+    }
+
+    // degree bound the last codeword is checked against
+    pub fn last_degree(&self) -> usize {
+        self.last_codeword_len() / usize::try_from(self.expansion_factor).unwrap() - 1
+    }
+
+    // number of bytes prove will push to the channel, every
+    // root, field element, path node and nonce is 32 bytes
+    pub fn proof_size_estimate(&self) -> usize {
+        let round_count = usize::try_from(self.round_count()).unwrap();
+        let test_count = usize::try_from(self.colinearity_test_count).unwrap();
+        let domain_len = usize::try_from(self.domain_len).unwrap();
+        // a path holds both nodes of each level below the root
+        let path_len = |len: usize| 2 * usize::try_from(len.ilog2()).unwrap();
+
+        let mut words = round_count;
+        words += self.last_codeword_len();
+        if self.pow_bits > 0 {
+            words += 1;
+        }
+        for i in 0..(round_count - 1) {
+            let current_len = domain_len >> i;
+            let next_len = domain_len >> (i + 1);
+            // three points then a path for each of them
+            words += test_count * (3 + 2 * path_len(current_len) + path_len(next_len));
+        }
+        32 * words
+    }
+
+    pub fn prove<H: ChannelHasher + TreeHasher>(
+        &self,
+        codeword: &Vec<T>,
+        channel: &mut Channel<H>,
+    ) -> Vec<u32> {
         if self.domain_len != u32::try_from(codeword.len()).unwrap() {
             panic!("initial codeword does not match domain len");
         }
-        let codewords = self.commit(codeword, channel);
-        let top_indices = self.sample_indices(
+        let (codewords, codeword_trees) = self.commit(codeword, channel);
+        if self.pow_bits > 0 {
+            let nonce = self.grind(&channel.prover_hash());
+            channel.push_single(&nonce);
+        }
+        let top_indices = match self.sample_indices(
             &channel.prover_hash(),
             codewords[1].len().try_into().unwrap(),
             codewords[codewords.len() - 1].len().try_into().unwrap(),
             self.colinearity_test_count,
-        );
+        ) {
+            Ok(indices) => indices,
+            Err(e) => panic!("{}", e),
+        };
         let mut indices: Vec<u32> = top_indices.clone();
-        let codeword_trees: Vec<Tree<T>> = codewords
-            .iter()
-            .map(|word| {
-                Tree::build(
-                    &word
-                        .iter()
-                        .map(|t| t.to_bytes_le_sized())
-                        .collect::<Vec<[u8; 32]>>(),
-                )
-            })
-            .collect();
         for i in 0..(codewords.len() - 1) {
             indices = indices
                 .iter()
@@ -96,14 +412,69 @@ impl<T: FieldElement> Fri<T> {
         top_indices
     }
 
-    fn query(
+    // prove that several codewords are all of low degree by
+    // running a single fri on a random linear combination,
+    // returns the opened points of each codeword
+    pub fn prove_batch<H: ChannelHasher + TreeHasher>(
+        &self,
+        codewords: &[Vec<T>],
+        channel: &mut Channel<H>,
+    ) -> Vec<Vec<(u32, T)>> {
+        let trees: Vec<Tree<T, H>> = codewords
+            .iter()
+            .map(|word| {
+                Tree::build(
+                    &word
+                        .iter()
+                        .map(|t| t.to_bytes_le_sized())
+                        .collect::<Vec<[u8; 32]>>(),
+                )
+            })
+            .collect();
+        for tree in &trees {
+            channel.push_single_labeled(BATCH_ROOT_LABEL, &tree.root());
+        }
+
+        let weights = self.batch_weights(codewords.len(), &channel.prover_hash());
+        let mut combined = vec![self.field.zero(); usize::try_from(self.domain_len).unwrap()];
+        for (codeword, weight) in codewords.iter().zip(&weights) {
+            for (i, v) in codeword.iter().enumerate() {
+                combined[i] = self.field.add(&combined[i], &self.field.mul(v, weight));
+            }
+        }
+        let top_indices = self.prove(&combined, channel);
+
+        // open each codeword at the points the first round tested
+        let half = self.domain_len >> 1;
+        let mut out = vec![Vec::new(); codewords.len()];
+        for top_index in top_indices {
+            let index_a = top_index % half;
+            for index in [index_a, index_a + half] {
+                for (i, codeword) in codewords.iter().enumerate() {
+                    let v = &codeword[usize::try_from(index).unwrap()];
+                    channel.push_single(&v.to_bytes_le_sized());
+                    channel.push(&trees[i].open(index).0);
+                    out[i].push((index, v.clone()));
+                }
+            }
+        }
+        out
+    }
+
+    // 1, alpha, alpha^2, ... sampled after the codeword roots
+    fn batch_weights(&self, count: usize, seed: &[u8; 32]) -> Vec<T> {
+        let alpha = self.field.sample_bytes(seed);
+        self.field.power_series(&alpha, count)
+    }
+
+    fn query<H: ChannelHasher + TreeHasher>(
         &self,
         current_codeword: &Vec<T>,
         next_codeword: &[T],
         indices_c: &[u32],
-        channel: &mut Channel,
-        current_codeword_tree: &Tree<T>,
-        next_codeword_tree: &Tree<T>,
+        channel: &mut Channel<H>,
+        current_codeword_tree: &Tree<T, H>,
+        next_codeword_tree: &Tree<T, H>,
     ) {
         let indices_a: Vec<u32> = indices_c.to_vec();
         let indices_b: Vec<u32> = indices_c
@@ -111,10 +482,10 @@ impl<T: FieldElement> Fri<T> {
             .map(|val| val + ((current_codeword.len() >> 1) as u32))
             .collect();
         for i in 0..usize::try_from(self.colinearity_test_count).unwrap() {
-            channel.push(&[
-                current_codeword[usize::try_from(indices_a[i]).unwrap()].to_bytes_le_sized(),
-                current_codeword[usize::try_from(indices_b[i]).unwrap()].to_bytes_le_sized(),
-                next_codeword[usize::try_from(indices_c[i]).unwrap()].to_bytes_le_sized(),
+            channel.push_triple(&[
+                current_codeword[usize::try_from(indices_a[i]).unwrap()].clone(),
+                current_codeword[usize::try_from(indices_b[i]).unwrap()].clone(),
+                next_codeword[usize::try_from(indices_c[i]).unwrap()].clone(),
             ]);
         }
         for i in 0..usize::try_from(self.colinearity_test_count).unwrap() {
@@ -124,10 +495,16 @@ impl<T: FieldElement> Fri<T> {
         }
     }
 
-    fn commit(&self, codeword: &[T], channel: &mut Channel) -> Vec<Vec<T>> {
+    // returns each round's codeword along with the tree
+    // its root was committed from
+    fn commit<H: ChannelHasher + TreeHasher>(
+        &self,
+        codeword: &[T],
+        channel: &mut Channel<H>,
+    ) -> (Vec<Vec<T>>, Vec<Tree<T, H>>) {
         let mut codewords = Vec::new();
+        let mut trees = Vec::new();
         let mut codeword = codeword.to_owned();
-        let two_inv = self.field.inv(&self.field.two());
 
         // invert the entire domain using repeated multiplications
         // e.g. 1/4 = (1/2) * (1/2)
@@ -143,8 +520,14 @@ impl<T: FieldElement> Fri<T> {
         let mut exp: usize = 1;
 
         for x in 0..self.round_count() {
-            let root = Tree::commit_elements(&codeword);
-            channel.push_single(&root);
+            let tree = Tree::build(
+                &codeword
+                    .iter()
+                    .map(|t| t.to_bytes_le_sized())
+                    .collect::<Vec<[u8; 32]>>(),
+            );
+            channel.push_single_labeled(ROOT_LABEL, &tree.root());
+            trees.push(tree);
             if x == self.round_count() - 1 {
                 break;
             }
@@ -154,43 +537,132 @@ impl<T: FieldElement> Fri<T> {
             // of the previous codewords, similar to a FFT
             let alpha = self.field.sample_bytes(&channel.prover_hash());
 
-            let next_len = codeword.len() >> 1;
-            codeword = codeword[0..next_len]
-                .iter()
-                .enumerate()
-                .map(|(index, val)| {
-                    let inv_omega = self
-                        .field
-                        .mul(&inv_offset_domain[exp], &inv_domain[exp * index]);
-                    // ( (one + alpha / (offset * (omega^i)) ) * codeword[i]
-                    let a = self.field.mul(
-                        val,
-                        &self
-                            .field
-                            .add(&self.field.one(), &self.field.mul(&alpha, &inv_omega)),
-                    );
-                    //  (one - alpha / (offset * (omega^i)) ) * codeword[len(codeword)//2 + i] ) for i in range(len(codeword)//2)]
-                    let b = self.field.mul(
-                        &self
-                            .field
-                            .sub(&self.field.one(), &self.field.mul(&alpha, &inv_omega)),
-                        &codeword[(codeword.len() >> 1) + index],
-                    );
-                    self.field.mul(&two_inv, &self.field.add(&a, &b))
-                })
-                .collect();
+            codeword = Self::fold(
+                &self.field,
+                &codeword,
+                &alpha,
+                &inv_offset_domain[exp],
+                &inv_domain,
+                exp,
+            );
 
             exp *= 2;
         }
 
-        channel.push(
+        channel.push_labeled(
+            LAST_CODEWORD_LABEL,
             &codeword
                 .iter()
                 .map(|t| t.to_bytes_le_sized())
                 .collect::<Vec<[u8; 32]>>(),
         );
         codewords.push(codeword);
-        codewords
+        (codewords, trees)
+    }
+
+    // search for a nonce such that hash(seed || nonce) has
+    // pow_bits leading zero bits
+    fn grind(&self, seed: &[u8; 32]) -> [u8; 32] {
+        let mut counter = 0_u64;
+        loop {
+            let mut nonce = [0_u8; 32];
+            nonce[0..8].copy_from_slice(&counter.to_le_bytes());
+            if Self::check_pow(seed, &nonce, self.pow_bits) {
+                return nonce;
+            }
+            counter += 1;
+        }
+    }
+
+    fn check_pow(seed: &[u8; 32], nonce: &[u8; 32], pow_bits: u32) -> bool {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(seed);
+        hasher.update(nonce);
+        let mut zeros = 0;
+        for byte in hasher.finalize().as_bytes() {
+            zeros += byte.leading_zeros();
+            if *byte != 0 || zeros >= pow_bits {
+                break;
+            }
+        }
+        zeros >= pow_bits
+    }
+
+    // combine the points at index and len/2 + index into
+    // one point of a polynomial of half the degree
+    fn fold_point(
+        field: &Field<T>,
+        codeword: &[T],
+        index: usize,
+        alpha: &T,
+        inv_x: &T,
+        two_inv: &T,
+    ) -> T {
+        // ( (one + alpha / (offset * (omega^i)) ) * codeword[i]
+        let a = field.mul(
+            &codeword[index],
+            &field.add(&field.one(), &field.mul(alpha, inv_x)),
+        );
+        //  (one - alpha / (offset * (omega^i)) ) * codeword[len(codeword)//2 + i] ) for i in range(len(codeword)//2)]
+        let b = field.mul(
+            &field.sub(&field.one(), &field.mul(alpha, inv_x)),
+            &codeword[(codeword.len() >> 1) + index],
+        );
+        field.mul(two_inv, &field.add(&a, &b))
+    }
+
+    // only used by tests when rayon is enabled
+    #[cfg_attr(feature = "rayon", allow(dead_code))]
+    fn fold_serial(
+        field: &Field<T>,
+        codeword: &[T],
+        alpha: &T,
+        inv_offset: &T,
+        inv_domain: &[T],
+        exp: usize,
+    ) -> Vec<T> {
+        let two_inv = field.inv(&field.two());
+        (0..(codeword.len() >> 1))
+            .map(|index| {
+                let inv_x = field.mul(inv_offset, &inv_domain[exp * index]);
+                Self::fold_point(field, codeword, index, alpha, &inv_x, &two_inv)
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "rayon")]
+    fn fold_parallel(
+        field: &Field<T>,
+        codeword: &[T],
+        alpha: &T,
+        inv_offset: &T,
+        inv_domain: &[T],
+        exp: usize,
+    ) -> Vec<T> {
+        let two_inv = field.inv(&field.two());
+        (0..(codeword.len() >> 1))
+            .into_par_iter()
+            .map(|index| {
+                let inv_x = field.mul(inv_offset, &inv_domain[exp * index]);
+                Self::fold_point(field, codeword, index, alpha, &inv_x, &two_inv)
+            })
+            .collect()
+    }
+
+    // threads aren't available in wasm so the serial fold
+    // is used unless the rayon feature is enabled
+    fn fold(
+        field: &Field<T>,
+        codeword: &[T],
+        alpha: &T,
+        inv_offset: &T,
+        inv_domain: &[T],
+        exp: usize,
+    ) -> Vec<T> {
+        #[cfg(feature = "rayon")]
+        return Self::fold_parallel(field, codeword, alpha, inv_offset, inv_domain, exp);
+        #[cfg(not(feature = "rayon"))]
+        return Self::fold_serial(field, codeword, alpha, inv_offset, inv_domain, exp);
     }
 
     fn sample_indices(
@@ -199,18 +671,25 @@ impl<T: FieldElement> Fri<T> {
         size: u32,
         reduced_size: u32,
         count: u32,
-    ) -> Vec<u32> {
-        if count > 2 * reduced_size {
-            panic!("not enough entropy");
-        }
+    ) -> Result<Vec<u32>, FriError> {
+        // every index must reduce to a distinct reduced index
         if count > reduced_size {
-            panic!("cannot sample more indices than available");
+            return Err(FriError::TooManyIndices {
+                count,
+                reduced_size,
+                max: reduced_size,
+            });
         }
 
         let mut indices: Vec<u32> = Vec::new();
         let mut reduced_indices: HashMap<u32, bool> = HashMap::new();
         let mut counter: u32 = 0;
         while indices.len() < (count as usize) {
+            if counter >= self.sample_attempts {
+                return Err(FriError::SamplingExhausted {
+                    attempts: self.sample_attempts,
+                });
+            }
             let mut hasher = blake3::Hasher::new();
             hasher.update(seed);
             hasher.update(&T::from_u32(counter, self.field.p()).to_bytes_le());
@@ -229,10 +708,119 @@ impl<T: FieldElement> Fri<T> {
                 true
             });
         }
-        indices
+        Ok(indices)
     }
 
-    pub fn verify(&self, channel: &mut Channel) -> Vec<(u32, T)> {
+    // prove on a local channel and collect what was pushed
+    pub fn prove_to_proof(&self, codeword: &Vec<T>) -> FriProof {
+        let mut channel = Channel::new();
+        self.prove(codeword, &mut channel);
+        self.pull_proof(&mut channel).unwrap()
+    }
+
+    // read the messages prove pushed back into a proof, None
+    // if they don't have the shape implied by the options
+    pub fn pull_proof<H: ChannelHasher>(&self, channel: &mut Channel<H>) -> Option<FriProof> {
+        let mut roots = Vec::new();
+        for _ in 0..self.round_count() {
+            roots.push(channel.try_pull_root()?);
+        }
+        let last_codeword = channel.try_pull_path()?;
+        let nonce = if self.pow_bits > 0 {
+            Some(channel.try_pull_root()?)
+        } else {
+            None
+        };
+        let test_count = usize::try_from(self.colinearity_test_count).unwrap();
+        let mut rounds = Vec::new();
+        for _ in 0..(self.round_count() - 1) {
+            let mut points = Vec::new();
+            for _ in 0..test_count {
+                let p: [[u8; 32]; 3] = channel.try_pull_path()?.try_into().ok()?;
+                points.push(p);
+            }
+            let mut queries = Vec::new();
+            for points in points {
+                queries.push(FriQuery {
+                    points,
+                    paths: [
+                        channel.try_pull_path()?,
+                        channel.try_pull_path()?,
+                        channel.try_pull_path()?,
+                    ],
+                });
+            }
+            rounds.push(queries);
+        }
+        Some(FriProof {
+            roots,
+            last_codeword,
+            nonce,
+            rounds,
+        })
+    }
+
+    // replay a proof through a channel in the order prove
+    // pushed it
+    pub fn verify_proof(&self, proof: &FriProof) -> Result<Vec<(u32, T)>, FriError> {
+        let mut channel = Channel::new();
+        self.push_proof(proof, &mut channel)?;
+        self.verify(&mut channel)
+    }
+
+    // the inverse of pull_proof, fails if the proof doesn't
+    // have the shape implied by the options
+    pub fn push_proof<H: ChannelHasher>(
+        &self,
+        proof: &FriProof,
+        channel: &mut Channel<H>,
+    ) -> Result<(), FriError> {
+        let round_count = usize::try_from(self.round_count()).unwrap();
+        let test_count = usize::try_from(self.colinearity_test_count).unwrap();
+        if proof.roots.len() != round_count
+            || proof.nonce.is_some() != (self.pow_bits > 0)
+            || proof.rounds.len() != round_count - 1
+            || proof
+                .rounds
+                .iter()
+                .any(|queries| queries.len() != test_count)
+        {
+            return Err(FriError::MalformedProof);
+        }
+        for root in &proof.roots {
+            channel.push_single_labeled(ROOT_LABEL, root);
+        }
+        channel.push_labeled(LAST_CODEWORD_LABEL, &proof.last_codeword);
+        if let Some(nonce) = &proof.nonce {
+            channel.push_single(nonce);
+        }
+        for queries in &proof.rounds {
+            for query in queries {
+                channel.push(&query.points);
+            }
+            for query in queries {
+                for path in &query.paths {
+                    channel.push(path);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn verify_or_panic<H: ChannelHasher + TreeHasher>(
+        &self,
+        channel: &mut Channel<H>,
+    ) -> Vec<(u32, T)> {
+        match self.verify(channel) {
+            Ok(out) => out,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn verify<H: ChannelHasher + TreeHasher>(
+        &self,
+        channel: &mut Channel<H>,
+    ) -> Result<Vec<(u32, T)>, FriError> {
         let mut out = Vec::new();
         let mut offset = self.offset.clone();
 
@@ -240,20 +828,31 @@ impl<T: FieldElement> Fri<T> {
         let mut alphas = Vec::new();
 
         for _ in 0..self.round_count() {
-            roots.push(channel.pull_root());
+            roots.push(
+                channel
+                    .pull_labeled_root(ROOT_LABEL)
+                    .ok_or(FriError::MalformedProof)?,
+            );
             alphas.push(self.field.sample_bytes(&channel.verifier_hash()));
         }
 
-        let last_codeword = channel.pull_path();
-        if roots[roots.len() - 1] != Tree::<T>::commit(&last_codeword) {
-            panic!("last codeword root mismatch");
+        let last_codeword = channel
+            .pull_labeled_path(LAST_CODEWORD_LABEL)
+            .ok_or(FriError::MalformedProof)?;
+        if last_codeword.len() != self.last_codeword_len() {
+            return Err(FriError::LastCodewordLength {
+                len: last_codeword.len(),
+                expected: self.last_codeword_len(),
+            });
+        }
+        if roots[roots.len() - 1] != Tree::<T, H>::commit(&last_codeword) {
+            return Err(FriError::LastCodewordRootMismatch);
         }
 
-        let degree: usize =
-            (last_codeword.len() / usize::try_from(self.expansion_factor).unwrap()) - 1;
+        let degree = self.last_degree();
         let mut last_offset = offset.clone();
         for _ in 0..(self.round_count() - 1) {
-            last_offset = self.field.mul(&last_offset, &last_offset);
+            last_offset = self.field.square(&last_offset);
         }
 
         let omega_domain = self.field.domain(&self.omega, self.domain_len);
@@ -264,7 +863,7 @@ impl<T: FieldElement> Fri<T> {
                 &T::from_u32((last_codeword.len() - 1) as u32, self.field.p()),
             )
         {
-            panic!("omega order incorrect");
+            return Err(FriError::OmegaOrderIncorrect);
         }
 
         let last_domain = last_codeword
@@ -284,13 +883,29 @@ impl<T: FieldElement> Fri<T> {
                 .collect(),
             &self.field,
         );
+        let last_evaluations = poly.eval_coset(
+            &last_offset,
+            &omega_domain[omega_start_index],
+            u32::try_from(last_domain.len()).unwrap(),
+        );
         for i in 0..last_domain.len() {
-            if poly.eval(&last_domain[i]) != T::from_bytes_le(&last_codeword[i], self.field.p()) {
-                panic!("interpolated polynomial is incorrect");
+            if last_evaluations[i] != T::from_bytes_le(&last_codeword[i], self.field.p()) {
+                return Err(FriError::InterpolationMismatch);
             }
         }
         if poly.degree() > degree {
-            panic!("last codeword does not match polynomial of low enough degree");
+            return Err(FriError::DegreeTooHigh {
+                degree: poly.degree(),
+                max: degree,
+            });
+        }
+
+        if self.pow_bits > 0 {
+            let seed = channel.verifier_hash();
+            let nonce = channel.try_pull_root().ok_or(FriError::MalformedProof)?;
+            if !Self::check_pow(&seed, &nonce, self.pow_bits) {
+                return Err(FriError::InvalidProofOfWork);
+            }
         }
 
         let top_indices = self.sample_indices(
@@ -298,11 +913,11 @@ impl<T: FieldElement> Fri<T> {
             self.domain_len >> 1,
             self.domain_len >> (self.round_count() - 1),
             self.colinearity_test_count,
-        );
-        let mut colinearity_x_vals = Vec::new();
-        let mut colinearity_y_vals = Vec::new();
+        )?;
         let mut exp = 1;
         for i in 0..usize::try_from(self.round_count() - 1).unwrap() {
+            let mut colinearity_x_vals = Vec::new();
+            let mut colinearity_y_vals = Vec::new();
             let indices_c: Vec<u32> = top_indices
                 .iter()
                 .map(|val| val % (self.domain_len >> (i + 1)))
@@ -317,10 +932,9 @@ impl<T: FieldElement> Fri<T> {
             let mut bb = Vec::new();
             let mut cc = Vec::new();
             for j in 0..usize::try_from(self.colinearity_test_count).unwrap() {
-                let y_points_msg = channel.pull_path();
-                let ay = T::from_bytes_le(&y_points_msg[0], self.field.p());
-                let by = T::from_bytes_le(&y_points_msg[1], self.field.p());
-                let cy = T::from_bytes_le(&y_points_msg[2], self.field.p());
+                let [ay, by, cy] = channel
+                    .try_pull_triple::<T>(self.field.p())
+                    .ok_or(FriError::MalformedProof)?;
                 aa.push(ay.clone());
                 bb.push(by.clone());
                 cc.push(cy.clone());
@@ -346,54 +960,89 @@ impl<T: FieldElement> Fri<T> {
                 colinearity_y_vals.push(vec![ay, by, cy]);
             }
 
-            if !Polynomial::test_colinearity_batch(
-                &colinearity_x_vals,
-                &colinearity_y_vals,
-                &self.field,
-            ) {
-                panic!("colinearity test failed");
+            if let Some(query) =
+                Polynomial::find_non_colinear(&colinearity_x_vals, &colinearity_y_vals, &self.field)
+            {
+                return Err(FriError::ColinearityFailed { round: i, query });
             }
 
             for j in 0..usize::try_from(self.colinearity_test_count).unwrap() {
-                Tree::<T>::verify(
+                Tree::<T, H>::verify(
                     &roots[i],
                     indices_a[j],
-                    &channel.pull_path(),
+                    &channel.try_pull_path().ok_or(FriError::MalformedProof)?,
                     &aa[j].to_bytes_le_sized(),
-                );
-                Tree::<T>::verify(
+                )
+                .map_err(|error| FriError::InvalidPath { round: i, error })?;
+                Tree::<T, H>::verify(
                     &roots[i],
                     indices_b[j],
-                    &channel.pull_path(),
+                    &channel.try_pull_path().ok_or(FriError::MalformedProof)?,
                     &bb[j].to_bytes_le_sized(),
-                );
-                Tree::<T>::verify(
+                )
+                .map_err(|error| FriError::InvalidPath { round: i, error })?;
+                Tree::<T, H>::verify(
                     &roots[i + 1],
                     indices_c[j],
-                    &channel.pull_path(),
+                    &channel.try_pull_path().ok_or(FriError::MalformedProof)?,
                     &cc[j].to_bytes_le_sized(),
-                );
+                )
+                .map_err(|error| FriError::InvalidPath { round: i, error })?;
             }
 
             exp *= 2;
-            offset = self.field.mul(&offset, &offset);
+            offset = self.field.square(&offset);
         }
 
-        out
+        Ok(out)
+    }
+
+    pub fn verify_batch<H: ChannelHasher + TreeHasher>(
+        &self,
+        codeword_count: usize,
+        channel: &mut Channel<H>,
+    ) -> Result<Vec<Vec<(u32, T)>>, FriError> {
+        let mut roots = Vec::new();
+        for _ in 0..codeword_count {
+            roots.push(
+                channel
+                    .pull_labeled_root(BATCH_ROOT_LABEL)
+                    .ok_or(FriError::MalformedProof)?,
+            );
+        }
+        let weights = self.batch_weights(codeword_count, &channel.verifier_hash());
+
+        let combined_points = self.verify(channel)?;
+
+        let mut out = vec![Vec::new(); codeword_count];
+        for (index, combined) in combined_points {
+            let mut sum = self.field.zero();
+            for i in 0..codeword_count {
+                let leaf = channel.try_pull_root().ok_or(FriError::MalformedProof)?;
+                let path = channel.try_pull_path().ok_or(FriError::MalformedProof)?;
+                Tree::<T, H>::verify(&roots[i], index, &path, &leaf)
+                    .map_err(|error| FriError::InvalidBatchPath { codeword: i, error })?;
+                let v = T::from_bytes_le(&leaf, self.field.p());
+                sum = self.field.add(&sum, &self.field.mul(&v, &weights[i]));
+                out[i].push((index, v));
+            }
+            if sum != combined {
+                return Err(FriError::BatchCombinationMismatch { index });
+            }
+        }
+        Ok(out)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::field_element::{ParamWrapper, CryptoBigIntElement, UC};
+    use crate::tree::KeccakHasher;
     use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
 
     use super::*;
 
-    #[test]
-    fn should_make_verify_fri_proof() {
-        let mut channel = Channel::new();
-
+    fn test_fri(pow_bits: u32, final_degree: u32) -> Fri<CryptoBigIntElement> {
         let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(1_u128 + 407_u128 * 2_u128.pow(119))));
         let g = CryptoBigIntElement(DynResidue::new(&UC::from_u128(85408008396924667383611388730472331217_u128), p.0));
         let f = Rc::new(Field::new(g.clone()));
@@ -401,16 +1050,23 @@ mod tests {
         let domain_size: u32 = 8192;
         let domain_g = f.generator(f.biguint(domain_size));
 
-        let fri = Fri::new(
+        Fri::new(
             &FriOptions {
                 offset: g.clone(),
                 omega: domain_g.clone(),
                 domain_len: domain_size,
                 expansion_factor: 2,
                 colinearity_test_count: 10,
+                pow_bits,
+                final_degree,
             },
             &f,
-        );
+        )
+    }
+
+    fn prove_test_polynomial(fri: Fri<CryptoBigIntElement>) -> (Fri<CryptoBigIntElement>, Channel) {
+        let f = Rc::clone(&fri.field);
+        let mut channel = Channel::new();
 
         let mut poly = Polynomial::new(&f);
         poly.term(&f.bigint(3), 2);
@@ -419,6 +1075,349 @@ mod tests {
             points.push(poly.eval(i));
         }
         fri.prove(&points, &mut channel);
-        fri.verify(&mut channel);
+        (fri, channel)
+    }
+
+    #[test]
+    fn should_make_verify_fri_proof() {
+        let (fri, mut channel) = prove_test_polynomial(test_fri(0, 0));
+        fri.verify_or_panic(&mut channel);
+    }
+
+    fn fri_with_domain(domain_len: u32, expansion_factor: u32) -> Fri<CryptoBigIntElement> {
+        let fri = test_fri(0, 0);
+        Fri::new(
+            &FriOptions {
+                offset: fri.offset.clone(),
+                omega: fri.omega.clone(),
+                domain_len,
+                expansion_factor,
+                colinearity_test_count: 10,
+                pow_bits: 0,
+                final_degree: 0,
+            },
+            &fri.field,
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "fri domain_len must be a power of two")]
+    fn should_reject_non_power_of_two_domain() {
+        fri_with_domain(8191, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "fri expansion_factor must be a power of two")]
+    fn should_reject_non_power_of_two_expansion() {
+        fri_with_domain(8192, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "fri domain_len must be larger than expansion_factor")]
+    fn should_reject_domain_within_expansion() {
+        fri_with_domain(8, 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "fri domain_len is too small to fold twice")]
+    fn should_reject_domain_with_one_round() {
+        fri_with_domain(16, 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "fri domain_len is too small to fold twice")]
+    fn should_reject_domain_within_test_count() {
+        // 40 point queries need a codeword longer than 40
+        fri_with_domain(64, 2);
+    }
+
+    #[test]
+    fn should_reject_tampered_last_codeword() {
+        let (fri, mut channel) = prove_test_polynomial(test_fri(0, 0));
+        // the last codeword follows the round roots
+        let index = usize::try_from(fri.round_count()).unwrap();
+        channel.messages[index].data[0] ^= 1;
+        assert_eq!(
+            fri.verify(&mut channel),
+            Err(FriError::LastCodewordRootMismatch)
+        );
+    }
+
+    #[test]
+    fn should_reject_tampered_colinearity_point() {
+        let (fri, mut channel) = prove_test_polynomial(test_fri(0, 0));
+        // the first colinearity points follow the last codeword
+        let index = usize::try_from(fri.round_count()).unwrap() + 1;
+        channel.messages[index].data[0] ^= 1;
+        assert_eq!(
+            fri.verify(&mut channel),
+            Err(FriError::ColinearityFailed { round: 0, query: 0 })
+        );
+    }
+
+    #[test]
+    fn should_verify_fri_proof_with_grinding() {
+        let (fri, mut channel) = prove_test_polynomial(test_fri(12, 0));
+        fri.verify_or_panic(&mut channel);
+    }
+
+    #[test]
+    fn should_reject_wrong_grinding_nonce() {
+        let (fri, mut channel) = prove_test_polynomial(test_fri(12, 0));
+        // the nonce follows the last codeword
+        let index = usize::try_from(fri.round_count()).unwrap() + 1;
+        channel.messages[index].data[0] ^= 1;
+        assert_eq!(fri.verify(&mut channel), Err(FriError::InvalidProofOfWork));
+    }
+
+    #[test]
+    fn should_estimate_proof_size() {
+        for pow_bits in [0, 4] {
+            let (fri, channel) = prove_test_polynomial(test_fri(pow_bits, 0));
+            assert_eq!(fri.proof_size_estimate(), channel.byte_len());
+        }
+    }
+
+    #[test]
+    fn should_prove_verify_batch() {
+        let fri = test_fri(0, 0);
+        let f = Rc::clone(&fri.field);
+
+        let mut poly1 = Polynomial::new(&f);
+        poly1.term(&f.bigint(3), 2);
+        let mut poly2 = Polynomial::new(&f);
+        poly2.term(&f.bigint(7), 2);
+        poly2.term(&f.bigint(5), 0);
+        let codewords: Vec<Vec<CryptoBigIntElement>> = [poly1, poly2]
+            .iter()
+            .map(|poly| fri.domain().iter().map(|x| poly.eval(x)).collect())
+            .collect();
+
+        let mut channel = Channel::new();
+        let opened = fri.prove_batch(&codewords, &mut channel);
+        assert_eq!(fri.verify_batch(2, &mut channel), Ok(opened));
+    }
+
+    #[test]
+    fn should_verify_with_final_degree() {
+        let mut last_round_count = None;
+        for final_degree in [0, 40, 64, 200] {
+            let (fri, mut channel) = prove_test_polynomial(test_fri(0, final_degree));
+            assert!(fri.last_degree() >= usize::try_from(final_degree).unwrap());
+            if let Some(round_count) = last_round_count {
+                assert!(fri.round_count() <= round_count);
+            }
+            last_round_count = Some(fri.round_count());
+            fri.verify_or_panic(&mut channel);
+        }
+    }
+
+    #[test]
+    fn should_keep_two_rounds_for_large_final_degree() {
+        let field = Rc::clone(&test_fri(0, 0).field);
+        // 1024 / 4 / 2 is the largest degree two rounds leave
+        for final_degree in [128, 256, u32::MAX] {
+            let fri = Fri::new(
+                &FriOptions {
+                    offset: field.g().clone(),
+                    omega: field.generator(field.biguint(1024)),
+                    domain_len: 1024,
+                    expansion_factor: 4,
+                    colinearity_test_count: 10,
+                    pow_bits: 0,
+                    final_degree,
+                },
+                &field,
+            );
+            assert_eq!(fri.round_count(), 2);
+            let (fri, mut channel) = prove_test_polynomial(fri);
+            fri.verify_or_panic(&mut channel);
+        }
+    }
+
+    #[test]
+    fn should_commit_roots_of_returned_trees() {
+        let fri = test_fri(0, 0);
+        let f = Rc::clone(&fri.field);
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(3), 2);
+        let points: Vec<CryptoBigIntElement> = fri.domain().iter().map(|x| poly.eval(x)).collect();
+
+        let mut channel = Channel::new();
+        let (codewords, trees) = fri.commit(&points, &mut channel);
+        assert_eq!(codewords.len(), trees.len());
+        for (i, (codeword, tree)) in codewords.iter().zip(&trees).enumerate() {
+            assert_eq!(
+                tree.root(),
+                Tree::<CryptoBigIntElement>::commit_elements(codeword)
+            );
+            assert_eq!(channel.messages[i].data, tree.root().to_vec());
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn should_fold_same_in_parallel() {
+        let fri = test_fri(0, 0);
+        let f = Rc::clone(&fri.field);
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(3), 2);
+        poly.term(&f.bigint(9), 1);
+        let codeword: Vec<CryptoBigIntElement> =
+            fri.domain().iter().map(|x| poly.eval(x)).collect();
+
+        let alpha = f.sample_bytes(&[7_u8; 32]);
+        let inv_offset = f.inv(&fri.offset);
+        let inv_domain = f.domain(&f.inv(&fri.omega), fri.domain_len);
+        let serial = Fri::fold_serial(&f, &codeword, &alpha, &inv_offset, &inv_domain, 1);
+        let parallel = Fri::fold_parallel(&f, &codeword, &alpha, &inv_offset, &inv_domain, 1);
+        assert_eq!(serial.len(), codeword.len() / 2);
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn should_reject_truncated_channel() {
+        let (fri, channel) = prove_test_polynomial(test_fri(0, 0));
+        let round_count = usize::try_from(fri.round_count()).unwrap();
+        // cut inside the roots, at the colinearity points and
+        // at the last path
+        for len in [1, round_count + 1, channel.messages.len() - 1] {
+            let mut truncated = Channel::new_verifier(&channel.serialize());
+            truncated.messages.truncate(len);
+            assert_eq!(fri.verify(&mut truncated), Err(FriError::MalformedProof));
+        }
+
+        // a point message of the wrong width
+        let mut widened = Channel::new_verifier(&channel.serialize());
+        widened.messages[round_count + 1].data.extend([0; 32]);
+        assert_eq!(fri.verify(&mut widened), Err(FriError::MalformedProof));
+    }
+
+    #[test]
+    fn should_reject_unlabeled_commitments() {
+        let (fri, channel) = prove_test_polynomial(test_fri(0, 0));
+        let round_count = usize::try_from(fri.round_count()).unwrap();
+        assert_eq!(channel.messages[0].label.as_deref(), Some(ROOT_LABEL));
+        assert_eq!(
+            channel.messages[round_count].label.as_deref(),
+            Some(LAST_CODEWORD_LABEL)
+        );
+
+        // the same bytes without a label, or with another
+        // commitment's label, aren't accepted
+        for (index, label) in [(0, None), (round_count, Some(ROOT_LABEL))] {
+            let mut relabeled = Channel::new_verifier(&channel.serialize());
+            relabeled.messages[index].label = label.map(|l| l.to_string());
+            assert_eq!(fri.verify(&mut relabeled), Err(FriError::MalformedProof));
+        }
+    }
+
+    #[test]
+    fn should_reject_short_query_path() {
+        let (fri, mut channel) = prove_test_polynomial(test_fri(0, 0));
+        // the first path follows the points of every query in
+        // round 0, remove one hash from it
+        let test_count = usize::try_from(fri.colinearity_test_count).unwrap();
+        let index = usize::try_from(fri.round_count()).unwrap() + 1 + test_count;
+        let len = channel.messages[index].data.len();
+        channel.messages[index].data.truncate(len - 32);
+        assert_eq!(
+            fri.verify(&mut channel),
+            Err(FriError::InvalidPath {
+                round: 0,
+                error: TreeError::InvalidPathLength
+            })
+        );
+    }
+
+    #[test]
+    fn should_name_failing_colinearity_query() {
+        let (fri, mut channel) = prove_test_polynomial(test_fri(0, 0));
+        // each round pushes the points of every query and then
+        // three paths per query
+        let test_count = usize::try_from(fri.colinearity_test_count).unwrap();
+        let round_start = usize::try_from(fri.round_count()).unwrap() + 1;
+        let index = round_start + 4 * test_count + 3;
+        // corrupt the second point of query 3 in round 1
+        channel.messages[index].data[32] ^= 1;
+        assert_eq!(
+            fri.verify(&mut channel),
+            Err(FriError::ColinearityFailed { round: 1, query: 3 })
+        );
+    }
+
+    #[test]
+    fn should_round_trip_fri_proof() {
+        let fri = test_fri(4, 0);
+        let f = Rc::clone(&fri.field);
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(3), 2);
+        let points: Vec<CryptoBigIntElement> = fri.domain().iter().map(|x| poly.eval(x)).collect();
+
+        let proof = fri.prove_to_proof(&points);
+        let expected = fri.verify_proof(&proof).unwrap();
+
+        let decoded = FriProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded, proof);
+        assert_eq!(fri.verify_proof(&decoded), Ok(expected.clone()));
+
+        let json = serde_json::to_string(&proof).unwrap();
+        let decoded: FriProof = serde_json::from_str(&json).unwrap();
+        assert_eq!(fri.verify_proof(&decoded), Ok(expected));
+
+        let bytes = proof.to_bytes();
+        assert_eq!(FriProof::from_bytes(&bytes[..(bytes.len() - 1)]), None);
+
+        let mut truncated = proof.clone();
+        truncated.rounds.pop();
+        assert_eq!(fri.verify_proof(&truncated), Err(FriError::MalformedProof));
+    }
+
+    #[test]
+    fn should_sample_distinct_reduced_indices() {
+        let fri = test_fri(0, 0);
+        let indices = fri.sample_indices(&[3_u8; 32], 4096, 64, 10).unwrap();
+        assert_eq!(indices.len(), 10);
+        let mut reduced: Vec<u32> = indices.iter().map(|i| i % 64).collect();
+        reduced.sort();
+        reduced.dedup();
+        assert_eq!(reduced.len(), 10);
+        assert!(indices.iter().all(|i| *i < 4096));
+    }
+
+    #[test]
+    fn should_reject_sampling_too_many_indices() {
+        let fri = test_fri(0, 0);
+        assert_eq!(
+            fri.sample_indices(&[3_u8; 32], 4096, 8, 10),
+            Err(FriError::TooManyIndices {
+                count: 10,
+                reduced_size: 8,
+                max: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn should_stop_sampling_after_attempt_limit() {
+        let mut fri = test_fri(0, 0);
+        fri.sample_attempts = 5;
+        assert_eq!(
+            fri.sample_indices(&[3_u8; 32], 4096, 64, 10),
+            Err(FriError::SamplingExhausted { attempts: 5 })
+        );
+    }
+
+    #[test]
+    fn should_verify_fri_proof_over_keccak_channel() {
+        let fri = test_fri(0, 0);
+        let f = Rc::clone(&fri.field);
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(3), 2);
+        let points: Vec<CryptoBigIntElement> = fri.domain().iter().map(|x| poly.eval(x)).collect();
+
+        let mut channel = Channel::<KeccakHasher>::with_hasher();
+        fri.prove(&points, &mut channel);
+        fri.verify_or_panic(&mut channel);
     }
 }
@@ -7,20 +7,71 @@ pub mod stark;
 pub mod tree;
 pub mod field_element;
 
-use crate::field::Field;
+use crate::field::{Field, FieldError};
 use crate::mpolynomial::MPolynomial;
-use crate::stark::Stark;
-use crate::field_element::{G, FieldElement, CryptoBigIntElement};
+use crate::stark::{Stark, StarkConfig, StarkError, StarkProof};
+use crate::field_element::{dec_to_bytes_le, G, P, P_BITS, FieldElement, CryptoBigIntElement, ParamWrapper};
+use serde::de::IntoDeserializer;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputError {
+    // the trace has no rows
+    EmptyTrace,
+    // the trace rows have no registers
+    NoRegisters,
+    // the trace needs a larger power of two domain than the
+    // field has
+    TraceTooLarge,
+    // the prime isn't an odd decimal integer that fits an element
+    InvalidPrime(String),
+    // the generator isn't a nonzero decimal integer below the
+    // prime that generates its multiplicative group, or lies in
+    // the evaluation domain
+    InvalidGenerator,
+    // no generator could be found for the prime
+    Field(FieldError),
+    Stark(StarkError),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::EmptyTrace => write!(f, "empty trace"),
+            InputError::NoRegisters => write!(f, "trace has no registers"),
+            InputError::TraceTooLarge => write!(f, "trace is too large for the field"),
+            InputError::InvalidPrime(reason) => write!(f, "invalid prime: {}", reason),
+            InputError::InvalidGenerator => write!(f, "invalid generator"),
+            InputError::Field(error) => write!(f, "invalid field: {}", error),
+            InputError::Stark(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+impl From<StarkError> for InputError {
+    fn from(error: StarkError) -> Self {
+        InputError::Stark(error)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ProveInput<T: FieldElement> {
     trace: Vec<Vec<T>>,
     transition_constraints: Vec<HashMap<Vec<u32>, T>>,
     boundary: Vec<(u32, u32, T)>,
+    #[serde(default = "Vec::new")]
+    public_inputs: Vec<T>,
+    // decimal strings, the default field is used when absent
+    #[serde(default)]
+    prime: Option<String>,
+    #[serde(default)]
+    generator: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -29,69 +80,255 @@ pub struct VerifyInput<T: FieldElement> {
     register_count: u32,
     transition_constraints: Vec<HashMap<Vec<u32>, T>>,
     boundary: Vec<(u32, u32, T)>,
+    #[serde(default = "Vec::new")]
+    public_inputs: Vec<T>,
+    // decimal strings, the default field is used when absent
+    #[serde(default)]
+    prime: Option<String>,
+    #[serde(default)]
+    generator: Option<String>,
 }
 
-fn stark_(trace_len: u32, register_count: u32) -> Stark<CryptoBigIntElement> {
-    let f = Rc::new(Field::new(G));
-    Stark::<CryptoBigIntElement>::new(
-        &G,
-        &f,
-        register_count,
-        trace_len,
-        32, // expansion factor
-        26, // colinearity tests
-        2,  // constraint degree
-    )
+// a decimal integer below the prime, as an element of p
+fn element_(s: &str, p: &ParamWrapper) -> Option<CryptoBigIntElement> {
+    let mut bytes = dec_to_bytes_le(s)?;
+    if bytes.len() > P_BITS / 8 {
+        return None;
+    }
+    bytes.resize(32, 0);
+    let v = CryptoBigIntElement::from_bytes_le(&bytes, p);
+    // out of range values are silently reduced
+    if v.to_bytes_le_sized() != bytes[..] || v == CryptoBigIntElement::zero(p) {
+        return None;
+    }
+    Some(v)
 }
 
-#[wasm_bindgen]
-pub fn prove(input: JsValue) -> String {
-    let input: ProveInput<CryptoBigIntElement> = serde_wasm_bindgen::from_value(input).unwrap();
-
-    let register_count = input.trace[0].len();
-    for i in 1..input.trace.len() {
-        if input.trace[i].len() != register_count {
-            log("inconsistent trace register count");
-            panic!();
+fn field_(
+    prime: &Option<String>,
+    generator: &Option<String>,
+) -> Result<Rc<Field<CryptoBigIntElement>>, InputError> {
+    let f = match (prime, generator) {
+        (None, None) => Field::new(G),
+        (prime, generator) => {
+            let p = match prime {
+                Some(prime) => ParamWrapper::deserialize(prime.as_str().into_deserializer())
+                    .map_err(|e: serde::de::value::Error| {
+                        InputError::InvalidPrime(e.to_string())
+                    })?,
+                None => P,
+            };
+            match generator {
+                Some(g) => {
+                    let g = element_(g, &p).ok_or(InputError::InvalidGenerator)?;
+                    // a smaller subgroup may not contain the domains
+                    if !Field::is_generator(&g, &p).map_err(InputError::Field)? {
+                        return Err(InputError::InvalidGenerator);
+                    }
+                    Field::new(g)
+                }
+                None => Field::from_prime(&p).map_err(InputError::Field)?,
+            }
         }
+    };
+    Ok(Rc::new(f))
+}
+
+// inputs are deserialized in the default field, so values
+// must be below its prime before moving them into the
+// field being proven over
+fn rebase_(v: &CryptoBigIntElement, f: &Field<CryptoBigIntElement>) -> CryptoBigIntElement {
+    CryptoBigIntElement::from_bytes_le(&v.to_bytes_le(), f.p())
+}
+
+fn constraints_(
+    transition_constraints: &[HashMap<Vec<u32>, CryptoBigIntElement>],
+    f: &Rc<Field<CryptoBigIntElement>>,
+) -> Vec<MPolynomial<CryptoBigIntElement>> {
+    transition_constraints
+        .iter()
+        .map(|v| {
+            let map = v.iter().map(|(k, c)| (k.clone(), rebase_(c, f))).collect();
+            MPolynomial::from_map(&map, f)
+        })
+        .collect()
+}
+
+// check everything Stark::with_config would panic on
+fn stark_(
+    f: &Rc<Field<CryptoBigIntElement>>,
+    trace_len: u32,
+    register_count: u32,
+) -> Result<Stark<CryptoBigIntElement>, InputError> {
+    if trace_len == 0 {
+        return Err(InputError::EmptyTrace);
+    }
+    if register_count == 0 {
+        return Err(InputError::NoRegisters);
     }
-    let stark: Stark<CryptoBigIntElement> = stark_(
-        input.trace.len().try_into().unwrap(),
-        register_count.try_into().unwrap(),
-    );
+    // 26 queries at an expansion factor of 32
+    let config = StarkConfig::for_security_bits(128);
+    let fri_domain_len = config
+        .fri_domain_len(trace_len)
+        .filter(|len| len.ilog2() <= f.two_adicity())
+        .ok_or(InputError::TraceTooLarge)?;
+    let order = CryptoBigIntElement::from_u32(fri_domain_len, f.p());
+    if f.exp(f.g(), &order) == f.one() {
+        return Err(InputError::InvalidGenerator);
+    }
+    Ok(Stark::<CryptoBigIntElement>::with_config(
+        f,
+        register_count,
+        trace_len,
+        &config,
+    ))
+}
+
+// build the stark and statement an input describes and
+// hand them to prove
+fn prove_with_<R, F>(input: &ProveInput<CryptoBigIntElement>, prove: F) -> Result<R, InputError>
+where
+    F: Fn(
+        &Stark<CryptoBigIntElement>,
+        &Vec<Vec<CryptoBigIntElement>>,
+        &Vec<MPolynomial<CryptoBigIntElement>>,
+        &[(u32, u32, CryptoBigIntElement)],
+        &[CryptoBigIntElement],
+    ) -> Result<R, StarkError>,
+{
+    let register_count = match input.trace.first() {
+        Some(registers) => registers.len(),
+        None => return Err(InputError::EmptyTrace),
+    };
+    let f = field_(&input.prime, &input.generator)?;
+    let stark = stark_(
+        &f,
+        input
+            .trace
+            .len()
+            .try_into()
+            .map_err(|_| InputError::TraceTooLarge)?,
+        register_count
+            .try_into()
+            .map_err(|_| InputError::TraceTooLarge)?,
+    )?;
 
-    let transition_constraints = input
-        .transition_constraints
+    let transition_constraints = constraints_(&input.transition_constraints, &f);
+    let boundary_constraints: Vec<_> = input
+        .boundary
         .iter()
-        .map(|v| MPolynomial::from_map(v, stark.field()))
+        .map(|(v1, v2, v3)| (*v1, *v2, rebase_(v3, &f)))
         .collect();
-    let boundary_constraints = input
-        .boundary
+    let public_inputs: Vec<_> = input.public_inputs.iter().map(|v| rebase_(v, &f)).collect();
+    let trace = input
+        .trace
         .iter()
-        .map(|(v1, v2, v3)| (*v1, *v2, v3.clone()))
+        .map(|v| v.iter().map(|e| rebase_(e, &f)).collect())
         .collect();
-    let trace = input.trace.iter().map(|v| v.to_vec()).collect();
 
-    stark.prove(&trace, &transition_constraints, &boundary_constraints)
+    Ok(prove(
+        &stark,
+        &trace,
+        &transition_constraints,
+        &boundary_constraints,
+        &public_inputs,
+    )?)
 }
 
-#[wasm_bindgen]
-pub fn verify(proof: String, input: JsValue) {
-    let input: VerifyInput<CryptoBigIntElement> = serde_wasm_bindgen::from_value(input).unwrap();
-
-    let stark = stark_(input.trace_len, input.register_count);
+fn verify_with_<F>(input: &VerifyInput<CryptoBigIntElement>, verify: F) -> Result<bool, InputError>
+where
+    F: Fn(
+        &Stark<CryptoBigIntElement>,
+        &Vec<MPolynomial<CryptoBigIntElement>>,
+        &[(u32, u32, CryptoBigIntElement)],
+        &[CryptoBigIntElement],
+    ) -> Result<(), StarkError>,
+{
+    let f = field_(&input.prime, &input.generator)?;
+    let stark = stark_(&f, input.trace_len, input.register_count)?;
 
-    let transition_constraints: Vec<MPolynomial<CryptoBigIntElement>> = input
-        .transition_constraints
-        .iter()
-        .map(|v| MPolynomial::from_map(v, stark.field()))
-        .collect();
-    let boundary_constraints = input
+    let transition_constraints = constraints_(&input.transition_constraints, &f);
+    let boundary_constraints: Vec<_> = input
         .boundary
         .iter()
-        .map(|(v1, v2, v3)| (*v1, *v2, v3.clone()))
+        .map(|(v1, v2, v3)| (*v1, *v2, rebase_(v3, &f)))
         .collect();
-    stark.verify(&proof, &transition_constraints, &boundary_constraints);
+    let public_inputs: Vec<_> = input.public_inputs.iter().map(|v| rebase_(v, &f)).collect();
+    match verify(
+        &stark,
+        &transition_constraints,
+        &boundary_constraints,
+        &public_inputs,
+    ) {
+        Ok(()) => Ok(true),
+        // the statement is wrong rather than the proof
+        Err(
+            e @ (StarkError::InvalidBoundary { .. }
+            | StarkError::ConflictingBoundary { .. }
+            | StarkError::NoTransitionConstraints
+            | StarkError::ConstraintVariables { .. }
+            | StarkError::ConstraintDegree { .. }),
+        ) => Err(e.into()),
+        Err(_) => Ok(false),
+    }
+}
+
+fn prove_(input: &ProveInput<CryptoBigIntElement>) -> Result<String, InputError> {
+    prove_with_(input, Stark::prove)
+}
+
+fn verify_(proof: &str, input: &VerifyInput<CryptoBigIntElement>) -> Result<bool, InputError> {
+    verify_with_(input, |stark, tc, boundary, public_inputs| {
+        stark.verify(proof, tc, boundary, public_inputs)
+    })
+}
+
+fn prove_bytes_(input: &ProveInput<CryptoBigIntElement>) -> Result<Vec<u8>, InputError> {
+    prove_with_(input, |stark, trace, tc, boundary, public_inputs| {
+        Ok(stark
+            .prove_to_proof(trace, tc, boundary, public_inputs)?
+            .to_bytes())
+    })
+}
+
+fn verify_bytes_(
+    proof: &[u8],
+    input: &VerifyInput<CryptoBigIntElement>,
+) -> Result<bool, InputError> {
+    verify_with_(input, |stark, tc, boundary, public_inputs| {
+        let proof = StarkProof::from_bytes(proof).ok_or(StarkError::MalformedProof)?;
+        stark.verify_proof(&proof, tc, boundary, public_inputs)
+    })
+}
+
+// errors are thrown as js exceptions, a panic would abort
+// the whole module
+#[wasm_bindgen]
+pub fn prove(input: JsValue) -> Result<String, JsError> {
+    let input: ProveInput<CryptoBigIntElement> = serde_wasm_bindgen::from_value(input)?;
+    Ok(prove_(&input)?)
+}
+
+// false for an invalid or undecodable proof, a malformed
+// statement is thrown
+#[wasm_bindgen]
+pub fn verify(proof: String, input: JsValue) -> Result<bool, JsError> {
+    let input: VerifyInput<CryptoBigIntElement> = serde_wasm_bindgen::from_value(input)?;
+    Ok(verify_(&proof, &input)?)
+}
+
+// the binary StarkProof encoding, a Uint8Array in js and
+// much smaller than the string proof
+#[wasm_bindgen]
+pub fn prove_bytes(input: JsValue) -> Result<Vec<u8>, JsError> {
+    let input: ProveInput<CryptoBigIntElement> = serde_wasm_bindgen::from_value(input)?;
+    Ok(prove_bytes_(&input)?)
+}
+
+#[wasm_bindgen]
+pub fn verify_bytes(proof: &[u8], input: JsValue) -> Result<bool, JsError> {
+    let input: VerifyInput<CryptoBigIntElement> = serde_wasm_bindgen::from_value(input)?;
+    Ok(verify_bytes_(proof, &input)?)
 }
 
 #[wasm_bindgen]
@@ -111,3 +348,181 @@ extern "C" {
     #[wasm_bindgen(js_namespace = console, js_name = log)]
     fn log_many(a: &str, b: &str);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto_bigint::modular::runtime_mod::DynResidueParams;
+    use crate::field_element::UC;
+
+    const GOLDILOCKS: &str = "18446744069414584321";
+
+    // two registers squared each cycle, over the goldilocks field
+    fn squares_input() -> ProveInput<CryptoBigIntElement> {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u64(
+            18446744069414584321_u64,
+        )));
+        let f: Rc<Field<CryptoBigIntElement>> = Rc::new(Field::from_prime(&p).unwrap());
+        let mut trace = vec![vec![f.bigint(2), f.bigint(3)]];
+        while trace.len() < 8 {
+            let last = &trace[trace.len() - 1];
+            trace.push(vec![f.square(&last[0]), f.square(&last[1])]);
+        }
+        let x = MPolynomial::variables(5, &f);
+        let transition_constraints = (0..2)
+            .map(|i| x[1 + i].times(&x[1 + i]).minus(&x[3 + i]).exps().clone())
+            .collect();
+        let boundary = vec![
+            (0, 0, f.bigint(2)),
+            (0, 1, f.bigint(3)),
+            (7, 0, trace[7][0].clone()),
+            (7, 1, trace[7][1].clone()),
+        ];
+        ProveInput {
+            trace,
+            transition_constraints,
+            boundary,
+            public_inputs: Vec::new(),
+            prime: Some(GOLDILOCKS.to_string()),
+            generator: None,
+        }
+    }
+
+    fn verify_input(input: &ProveInput<CryptoBigIntElement>) -> VerifyInput<CryptoBigIntElement> {
+        VerifyInput {
+            trace_len: 8,
+            register_count: 2,
+            transition_constraints: input.transition_constraints.clone(),
+            boundary: input.boundary.clone(),
+            public_inputs: Vec::new(),
+            prime: input.prime.clone(),
+            generator: input.generator.clone(),
+        }
+    }
+
+    #[test]
+    fn should_prove_verify_with_custom_prime() {
+        let input = squares_input();
+        let mut verify_input = verify_input(&input);
+        let proof = prove_(&input).unwrap();
+        assert_eq!(verify_(&proof, &verify_input), Ok(true));
+
+        // 7 is the smallest primitive root, the one from_prime finds
+        verify_input.generator = Some("7".to_string());
+        assert_eq!(verify_(&proof, &verify_input), Ok(true));
+
+        verify_input.prime = None;
+        verify_input.generator = None;
+        assert_eq!(verify_(&proof, &verify_input), Ok(false));
+    }
+
+    #[test]
+    fn should_verify_to_bool() {
+        let input = squares_input();
+        let verify_input = verify_input(&input);
+        let proof = prove_(&input).unwrap();
+        assert_eq!(verify_(&proof, &verify_input), Ok(true));
+
+        // bump one byte of the proof, 0 to 1 keeps it below 256
+        let i = proof[proof.len() / 2..].find('0').unwrap() + proof.len() / 2;
+        let mut tampered = proof.clone();
+        tampered.replace_range(i..(i + 1), "1");
+        assert_eq!(verify_(&tampered, &verify_input), Ok(false));
+        assert_eq!(verify_("[]", &verify_input), Ok(false));
+        assert_eq!(verify_("not a proof", &verify_input), Ok(false));
+    }
+
+    #[test]
+    fn should_round_trip_proof_bytes() {
+        let input = squares_input();
+        let verify_input = verify_input(&input);
+        let mut proof = prove_bytes_(&input).unwrap();
+        assert!(proof.len() < prove_(&input).unwrap().len());
+        assert_eq!(verify_bytes_(&proof, &verify_input), Ok(true));
+
+        let last = proof.len() - 1;
+        proof[last] ^= 1;
+        assert_eq!(verify_bytes_(&proof, &verify_input), Ok(false));
+        assert_eq!(verify_bytes_(&proof[1..], &verify_input), Ok(false));
+
+        proof[last] ^= 1;
+        let mut truncated = StarkProof::from_bytes(&proof).unwrap();
+        truncated.randomizer_openings.truncate(1);
+        assert_eq!(
+            verify_bytes_(&truncated.to_bytes(), &verify_input),
+            Ok(false)
+        );
+
+        // a merkle path with one hash missing
+        let mut short_path = StarkProof::from_bytes(&proof).unwrap();
+        short_path.randomizer_openings[0].path.pop();
+        assert_eq!(
+            verify_bytes_(&short_path.to_bytes(), &verify_input),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn should_return_input_errors() {
+        let mut input = squares_input();
+        input.trace[3].pop();
+        assert_eq!(
+            prove_(&input),
+            Err(InputError::Stark(StarkError::InconsistentTrace {
+                row: 3,
+                len: 1,
+                expected: 2
+            }))
+        );
+        input.trace.clear();
+        assert_eq!(prove_(&input), Err(InputError::EmptyTrace));
+
+        let f = field_(&input.prime, &None).unwrap();
+        let mut verify_input = verify_input(&squares_input());
+        verify_input.register_count = 0;
+        assert_eq!(verify_("", &verify_input), Err(InputError::NoRegisters));
+        // goldilocks has a two-adicity of 32
+        verify_input.register_count = 2;
+        verify_input.trace_len = 1 << 30;
+        assert_eq!(verify_("", &verify_input), Err(InputError::TraceTooLarge));
+
+        // the verifier checks the statement before using it
+        let proof = prove_(&squares_input()).unwrap();
+        verify_input.trace_len = 8;
+        verify_input.transition_constraints[0].insert(vec![0, 0, 0, 0, 0, 0, 0, 1], f.one());
+        assert_eq!(
+            verify_(&proof, &verify_input),
+            Err(InputError::Stark(StarkError::ConstraintVariables {
+                count: 8,
+                max: 5
+            }))
+        );
+
+        let mut input = squares_input();
+        input.transition_constraints.clear();
+        assert_eq!(
+            prove_(&input),
+            Err(InputError::Stark(StarkError::NoTransitionConstraints))
+        );
+        verify_input.transition_constraints.clear();
+        assert_eq!(
+            verify_(&proof, &verify_input),
+            Err(InputError::Stark(StarkError::NoTransitionConstraints))
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_field() {
+        let mut input = squares_input();
+        input.prime = Some("18446744069414584320".to_string());
+        assert!(matches!(prove_(&input), Err(InputError::InvalidPrime(_))));
+
+        let mut input = squares_input();
+        input.generator = Some(GOLDILOCKS.to_string());
+        assert_eq!(prove_(&input), Err(InputError::InvalidGenerator));
+
+        // 2 has order 192 in goldilocks
+        input.generator = Some("2".to_string());
+        assert_eq!(prove_(&input), Err(InputError::InvalidGenerator));
+    }
+}
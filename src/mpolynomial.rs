@@ -1,8 +1,10 @@
 use crate::field::Field;
-use crate::polynomial::Polynomial;
+use crate::field_element::bytes_le_to_dec;
+use crate::polynomial::{Polynomial, SparsePolynomial};
 use crate::FieldElement;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 
 #[derive(Clone, Serialize)]
@@ -34,6 +36,25 @@ impl<T: FieldElement> MPolynomial<T> {
         &self.exp_map
     }
 
+    // largest sum of exponents over all terms, 0 for the
+    // zero polynomial
+    pub fn total_degree(&self) -> u32 {
+        self.exp_map
+            .keys()
+            .map(|exps| exps.iter().sum())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // largest exponent of variable var over all terms
+    pub fn degree_in(&self, var: usize) -> u32 {
+        self.exp_map
+            .keys()
+            .map(|exps| *exps.get(var).unwrap_or(&0))
+            .max()
+            .unwrap_or(0)
+    }
+
     // only works if both polynomials are trimmed
     pub fn is_equal(&self, p: &MPolynomial<T>) -> bool {
         if self.exp_map.len() != p.exps().len() {
@@ -106,9 +127,37 @@ impl<T: FieldElement> MPolynomial<T> {
         for (_exps, coef) in self.exp_map.iter_mut() {
             *coef = self.field.mul(coef, val);
         }
+        self.trim();
         self
     }
 
+    // non-mutating versions of add, sub, mul, and mul_scalar
+    // e.g. x0^2 - x1 is x(0).times(&x(0)).minus(&x(1))
+
+    pub fn plus(&self, poly: &MPolynomial<T>) -> MPolynomial<T> {
+        let mut out = self.clone();
+        out.add(poly);
+        out
+    }
+
+    pub fn minus(&self, poly: &MPolynomial<T>) -> MPolynomial<T> {
+        let mut out = self.clone();
+        out.sub(poly);
+        out
+    }
+
+    pub fn times(&self, poly: &MPolynomial<T>) -> MPolynomial<T> {
+        let mut out = self.clone();
+        out.mul(poly);
+        out
+    }
+
+    pub fn times_scalar(&self, v: &T) -> MPolynomial<T> {
+        let mut out = self.clone();
+        out.mul_scalar(v);
+        out
+    }
+
     pub fn mul(&mut self, poly: &MPolynomial<T>) -> &Self {
         let mut new_exp = HashMap::new();
         let zero = self.field.zero();
@@ -129,6 +178,8 @@ impl<T: FieldElement> MPolynomial<T> {
             }
         }
         self.exp_map = new_exp;
+        // cancelled terms are left with zero coefficients
+        self.trim();
         self
     }
 
@@ -152,6 +203,25 @@ impl<T: FieldElement> MPolynomial<T> {
         out
     }
 
+    // substitute a constant for one variable, the other
+    // variables keep their indices
+    pub fn partial_eval(&self, var: usize, value: &T) -> MPolynomial<T> {
+        let mut out = MPolynomial::new(&self.field);
+        for (exps, coef) in self.exps() {
+            let mut exps = exps.clone();
+            let mut coef = coef.clone();
+            if let Some(e) = exps.get_mut(var) {
+                coef = self.field.mul(
+                    &coef,
+                    &self.field.exp(value, &T::from_u32(*e, self.field.p())),
+                );
+                *e = 0;
+            }
+            out.term(&coef, &exps);
+        }
+        out
+    }
+
     pub fn eval_symbolic(&self, polys: &[Polynomial<T>]) -> Polynomial<T> {
         let mut out = Polynomial::new(&self.field);
         let mut degrees: Vec<u32> = Vec::new();
@@ -198,6 +268,31 @@ impl<T: FieldElement> MPolynomial<T> {
         out
     }
 
+    // eval_symbolic for inputs that are mostly zero coefficients
+    pub fn eval_symbolic_sparse(&self, polys: &[SparsePolynomial<T>]) -> SparsePolynomial<T> {
+        let mut out = SparsePolynomial::new(&self.field);
+        let mut one = SparsePolynomial::new(&self.field);
+        one.term(&self.field.one(), 0);
+        let mut power_map: Vec<Vec<SparsePolynomial<T>>> = vec![vec![one]; polys.len()];
+        for (exps, coef) in self.exps() {
+            let mut inter = SparsePolynomial::new(&self.field);
+            inter.term(coef, 0);
+            for (i, e) in exps.iter().enumerate() {
+                let e = usize::try_from(*e).unwrap();
+                while power_map[i].len() <= e {
+                    let mut next = power_map[i][power_map[i].len() - 1].clone();
+                    next.mul(&polys[i]);
+                    power_map[i].push(next);
+                }
+                if e > 0 {
+                    inter.mul(&power_map[i][e]);
+                }
+            }
+            out.add(&inter);
+        }
+        out
+    }
+
     pub fn from_poly(poly: &Polynomial<T>) -> MPolynomial<T> {
         let mut out = MPolynomial::new(poly.field());
         for (exp, coef) in poly.coefs().iter().enumerate() {
@@ -207,6 +302,21 @@ impl<T: FieldElement> MPolynomial<T> {
         out
     }
 
+    // the polynomial x_i
+    pub fn variable(i: usize, field: &Rc<Field<T>>) -> MPolynomial<T> {
+        let mut exps = vec![0; i];
+        exps.push(1);
+        let mut out = MPolynomial::new(field);
+        out.term(&field.one(), &exps);
+        out
+    }
+
+    pub fn constant(c: &T, field: &Rc<Field<T>>) -> MPolynomial<T> {
+        let mut out = MPolynomial::new(field);
+        out.term(c, &vec![0]);
+        out
+    }
+
     pub fn variables(count: u32, field: &Rc<Field<T>>) -> Vec<MPolynomial<T>> {
         let mut out = Vec::new();
         for i in 0..count {
@@ -218,6 +328,58 @@ impl<T: FieldElement> MPolynomial<T> {
         }
         out
     }
+
+    // sum of polys[i] * weights[i]
+    pub fn random_linear_combination(
+        polys: &[MPolynomial<T>],
+        weights: &[T],
+        field: &Rc<Field<T>>,
+    ) -> MPolynomial<T> {
+        assert_eq!(
+            polys.len(),
+            weights.len(),
+            "polynomial and weight count mismatch"
+        );
+        let mut out = MPolynomial::new(field);
+        for (poly, weight) in polys.iter().zip(weights) {
+            out.add(poly.clone().mul_scalar(weight));
+        }
+        out
+    }
+}
+
+// terms are ordered by exponent vector, largest first
+// e.g. 3*x0^2*x1 + 5*x2 + 1
+impl<T: FieldElement> fmt::Display for MPolynomial<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let zero = self.field.zero();
+        let one = self.field.one();
+        let mut exps: Vec<&Vec<u32>> = self.exp_map.keys().collect();
+        exps.sort_by(|a, b| b.cmp(a));
+        let mut terms = Vec::new();
+        for e in exps {
+            let coef = &self.exp_map[e];
+            if coef == &zero {
+                continue;
+            }
+            let mut factors = Vec::new();
+            for (i, exp) in e.iter().enumerate() {
+                match exp {
+                    0 => {}
+                    1 => factors.push(format!("x{}", i)),
+                    _ => factors.push(format!("x{}^{}", i, exp)),
+                }
+            }
+            if factors.is_empty() || coef != &one {
+                factors.insert(0, bytes_le_to_dec(&coef.to_bytes_le()));
+            }
+            terms.push(factors.join("*"));
+        }
+        if terms.is_empty() {
+            return write!(f, "0");
+        }
+        write!(f, "{}", terms.join(" + "))
+    }
 }
 
 #[cfg(test)]
@@ -295,6 +457,175 @@ mod tests {
         assert!(poly1.clone().mul(&poly2).is_equal(&expected));
     }
 
+    #[test]
+    fn should_mul_difference_of_squares() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
+        let g = CryptoBigIntElement::from_u32(0, &p);
+        let f = Rc::new(Field::new(g));
+
+        // x + y
+        let mut sum = MPolynomial::new(&f);
+        sum.term(&f.bigint(1), &vec![1]);
+        sum.term(&f.bigint(1), &vec![0, 1]);
+
+        // x - y
+        let mut diff = MPolynomial::new(&f);
+        diff.term(&f.bigint(1), &vec![1]);
+        diff.term(&f.bigint(-1), &vec![0, 1]);
+
+        // x^2 - y^2, the xy terms cancel
+        let mut expected_map = HashMap::new();
+        expected_map.insert(vec![2], f.bigint(1));
+        expected_map.insert(vec![0, 2], f.bigint(-1));
+        let expected = MPolynomial::from_map(&expected_map, &f);
+
+        let mut out = sum.clone();
+        out.mul(&diff);
+        assert!(out.is_equal(&expected));
+        assert_eq!(out.exps(), &expected_map);
+
+        // scaling then adding recovers the combination
+        let mut scaled = sum.clone();
+        scaled.mul_scalar(&f.bigint(3));
+        scaled.add(&diff);
+        let mut expected_combination = MPolynomial::new(&f);
+        expected_combination.term(&f.bigint(4), &vec![1]);
+        expected_combination.term(&f.bigint(2), &vec![0, 1]);
+        assert!(scaled.is_equal(&expected_combination));
+
+        sum.mul_scalar(&f.zero());
+        assert!(sum.exps().is_empty());
+    }
+
+    #[test]
+    fn should_compute_multipolynomial_degrees() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
+        let g = CryptoBigIntElement::from_u32(0, &p);
+        let f = Rc::new(Field::new(g));
+
+        // x^3*y + y^2
+        let mut poly = MPolynomial::new(&f);
+        poly.term(&f.bigint(1), &vec![3, 1]);
+        poly.term(&f.bigint(1), &vec![0, 2]);
+        assert_eq!(poly.total_degree(), 4);
+        assert_eq!(poly.degree_in(0), 3);
+        assert_eq!(poly.degree_in(1), 2);
+        assert_eq!(poly.degree_in(2), 0);
+
+        let zero = MPolynomial::new(&f);
+        assert_eq!(zero.total_degree(), 0);
+        assert_eq!(zero.degree_in(0), 0);
+    }
+
+    #[test]
+    fn should_display_multipolynomial() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
+        let g = CryptoBigIntElement::from_u32(0, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut map = HashMap::new();
+        map.insert(vec![0, 0, 1], f.bigint(5));
+        map.insert(vec![2, 1], f.bigint(3));
+        map.insert(vec![0, 1], f.bigint(1));
+        map.insert(vec![0], f.bigint(-1));
+        let poly = MPolynomial::from_map(&map, &f);
+        assert_eq!(poly.to_string(), "3*x0^2*x1 + x1 + 5*x2 + 100");
+        assert_eq!(MPolynomial::new(&f).to_string(), "0");
+    }
+
+    #[test]
+    fn should_partially_eval_multipolynomial() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
+        let g = CryptoBigIntElement::from_u32(0, &p);
+        let f = Rc::new(Field::new(g));
+
+        // x0*x1 + x1^2
+        let mut poly = MPolynomial::new(&f);
+        poly.term(&f.bigint(1), &vec![1, 1]);
+        poly.term(&f.bigint(1), &vec![0, 2]);
+
+        // x1: 2
+        // 2*x0 + 4
+        let mut expected = MPolynomial::new(&f);
+        expected.term(&f.bigint(2), &vec![1]);
+        expected.term(&f.bigint(4), &vec![0]);
+
+        let out = poly.partial_eval(1, &f.bigint(2));
+        assert!(out.is_equal(&expected));
+        assert_eq!(
+            out.eval(&[f.bigint(7)]),
+            poly.eval(&[f.bigint(7), f.bigint(2)])
+        );
+
+        // substituting a variable that doesn't appear changes nothing
+        assert!(poly.partial_eval(3, &f.bigint(2)).is_equal(&poly));
+    }
+
+    #[test]
+    fn should_build_multipolynomial_from_variables() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
+        let g = CryptoBigIntElement::from_u32(0, &p);
+        let f = Rc::new(Field::new(g));
+        let x = |i| MPolynomial::variable(i, &f);
+
+        // x0^2 - x1
+        let mut map = HashMap::new();
+        map.insert(vec![2], f.one());
+        map.insert(vec![0, 1], f.bigint(-1));
+        let expected = MPolynomial::from_map(&map, &f);
+        assert!(x(0).times(&x(0)).minus(&x(1)).is_equal(&expected));
+
+        // 3*x0*x2 + x1 + 7
+        let mut map = HashMap::new();
+        map.insert(vec![1, 0, 1], f.bigint(3));
+        map.insert(vec![0, 1], f.one());
+        map.insert(vec![0], f.bigint(7));
+        let expected = MPolynomial::from_map(&map, &f);
+        let built = x(0)
+            .times(&x(2))
+            .times_scalar(&f.bigint(3))
+            .plus(&x(1))
+            .plus(&MPolynomial::constant(&f.bigint(7), &f));
+        assert!(built.is_equal(&expected));
+
+        // the non-mutating versions leave the receiver unchanged
+        let x0 = x(0);
+        x0.plus(&x(1));
+        assert!(x0.is_equal(&x(0)));
+    }
+
+    #[test]
+    fn should_combine_multipolynomials() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
+        let g = CryptoBigIntElement::from_u32(0, &p);
+        let f = Rc::new(Field::new(g));
+
+        // x0*x1 + 3
+        let mut a = MPolynomial::new(&f);
+        a.term(&f.bigint(1), &vec![1, 1]);
+        a.term(&f.bigint(3), &vec![0]);
+
+        // x1^2 + x0
+        let mut b = MPolynomial::new(&f);
+        b.term(&f.bigint(1), &vec![0, 2]);
+        b.term(&f.bigint(1), &vec![1]);
+
+        let polys = vec![a.clone(), b.clone()];
+        let out = MPolynomial::random_linear_combination(&polys, &[f.one(), f.zero()], &f);
+        assert!(out.is_equal(&a));
+
+        // 2*a + 5*b
+        let out = MPolynomial::random_linear_combination(&polys, &[f.bigint(2), f.bigint(5)], &f);
+        let point = [f.bigint(4), f.bigint(9)];
+        assert_eq!(
+            out.eval(&point),
+            f.add(
+                &f.mul(&f.bigint(2), &a.eval(&point)),
+                &f.mul(&f.bigint(5), &b.eval(&point))
+            )
+        );
+    }
+
     #[test]
     fn should_eval_multipolynomial() {
         let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
@@ -314,6 +645,22 @@ mod tests {
         assert_eq!(poly.eval(&[f.bigint(50), f.bigint(20)]), expected);
     }
 
+    #[test]
+    fn should_eval_product_multipolynomial() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
+        let g = CryptoBigIntElement::from_u32(0, &p);
+        let f = Rc::new(Field::new(g));
+
+        // x*y + 1
+        let mut poly = MPolynomial::new(&f);
+        poly.term(&f.bigint(1), &vec![1, 1]);
+        poly.term(&f.bigint(1), &vec![0]);
+
+        assert_eq!(poly.eval(&[f.bigint(3), f.bigint(4)]), f.bigint(13));
+        // 50*60 + 1 = 3001 = 72 mod 101
+        assert_eq!(poly.eval(&[f.bigint(50), f.bigint(60)]), f.bigint(72));
+    }
+
     #[test]
     fn should_eval_symbolic_multipolynomial() {
         let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
@@ -341,6 +688,65 @@ mod tests {
         assert!(poly.eval_symbolic(&vec!(x, y)).is_equal(&expected));
     }
 
+    #[test]
+    fn should_eval_symbolic_sparse_multipolynomial() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+        // 4x + 2y^2 + 9
+        let mut poly = MPolynomial::new(&f);
+        poly.term(&f.bigint(4), &vec![1]);
+        poly.term(&f.bigint(2), &vec![0, 2]);
+        poly.term(&f.bigint(9), &vec![0]);
+
+        let mut x = Polynomial::new(&f);
+        x.term(&f.bigint(5), 2);
+
+        let mut y = Polynomial::new(&f);
+        y.term(&f.bigint(9), 6);
+
+        // the sparse substitution matches the dense one and
+        // keeps only the 3 nonzero terms
+        let expected = poly.eval_symbolic(&[x.clone(), y.clone()]);
+        let sparse = poly.eval_symbolic_sparse(&[
+            SparsePolynomial::from_dense(&x),
+            SparsePolynomial::from_dense(&y),
+        ]);
+        assert_eq!(sparse.terms().len(), 3);
+        assert!(sparse.to_dense().is_equal(&expected));
+    }
+
+    #[test]
+    fn should_substitute_univariate_polynomials() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+        // x^2 + y
+        let mut poly = MPolynomial::new(&f);
+        poly.term(&f.bigint(1), &vec![2]);
+        poly.term(&f.bigint(1), &vec![0, 1]);
+
+        // x: a^2, y: a
+        let mut x = Polynomial::new(&f);
+        x.term(&f.bigint(1), 2);
+        let mut y = Polynomial::new(&f);
+        y.term(&f.bigint(1), 1);
+
+        // a^4 + a
+        let mut expected = Polynomial::new(&f);
+        expected.term(&f.bigint(1), 4);
+        expected.term(&f.bigint(1), 1);
+        let out = poly.eval_symbolic(&[x.clone(), y.clone()]);
+        assert!(out.is_equal(&expected));
+
+        // x^2 + y + 3xy, with an unused third variable
+        // a^4 + a + 3a^3
+        poly.term(&f.bigint(3), &vec![1, 1]);
+        expected.term(&f.bigint(3), 3);
+        let z = Polynomial::new(&f);
+        assert!(poly.eval_symbolic(&[x, y, z]).is_equal(&expected));
+    }
+
     #[test]
     fn should_make_multipolynomial_from_polynomial() {
         let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(101_u128)));
@@ -1,10 +1,18 @@
-use crate::{field::Field, field_element::FieldElement};
+use crate::{
+    field::Field,
+    field_element::{bytes_le_to_dec, FieldElement},
+};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::rc::Rc;
 
 #[derive(Clone, Debug)]
 pub struct Polynomial<T: FieldElement> {
     field: Rc<Field<T>>,
     coefs: Vec<T>,
+    // highest non-zero exponent, None after a mutation
+    degree: Cell<Option<usize>>,
 }
 
 impl<T: FieldElement> Polynomial<T> {
@@ -16,14 +24,35 @@ impl<T: FieldElement> Polynomial<T> {
         Polynomial {
             field: Rc::clone(f),
             coefs: Vec::new(),
+            degree: Cell::new(None),
         }
     }
 
+    // coefficients ordered from lowest to highest degree
+    pub fn from_coefs(coefs: Vec<T>, f: &Rc<Field<T>>) -> Polynomial<T> {
+        let mut out = Polynomial {
+            field: Rc::clone(f),
+            coefs,
+            degree: Cell::new(None),
+        };
+        out.trim();
+        out
+    }
+
     pub fn coefs(&self) -> &Vec<T> {
         &self.coefs
     }
 
     pub fn degree(&self) -> usize {
+        if let Some(degree) = self.degree.get() {
+            return degree;
+        }
+        let degree = self.compute_degree();
+        self.degree.set(Some(degree));
+        degree
+    }
+
+    fn compute_degree(&self) -> usize {
         let zero = self.field().zero();
         for i in 0..self.coefs.len() {
             let index = (self.coefs.len() - 1) - i;
@@ -45,23 +74,17 @@ impl<T: FieldElement> Polynomial<T> {
     }
 
     pub fn is_equal(&self, poly: &Polynomial<T>) -> bool {
-        let zero = self.field().zero();
-        let larger;
-        let smaller;
-        if poly.coefs().len() > self.coefs().len() {
-            larger = poly.coefs();
-            smaller = self.coefs();
-        } else {
-            smaller = poly.coefs();
-            larger = self.coefs();
+        let degree = self.degree();
+        if degree != poly.degree() {
+            return false;
         }
-        for i in 0..smaller.len() {
-            if larger.get(i) != smaller.get(i) {
-                return false;
-            }
-        }
-        for i in smaller.len()..larger.len() {
-            if larger.get(i) != Some(&zero) {
+        // missing coefficients are zero, e.g. an empty
+        // polynomial is equal to a zero constant
+        let zero = self.field().zero();
+        for i in 0..=degree {
+            let a = self.coefs.get(i).unwrap_or(&zero);
+            let b = poly.coefs().get(i).unwrap_or(&zero);
+            if a != b {
                 return false;
             }
         }
@@ -69,6 +92,7 @@ impl<T: FieldElement> Polynomial<T> {
     }
 
     pub fn term(&mut self, coef: &T, exp: u32) -> &Self {
+        self.degree.set(None);
         let s = usize::try_from(exp).unwrap();
         // expand to one longer to handle 0 exponents
         if self.coefs.len() < s + 1 {
@@ -79,6 +103,7 @@ impl<T: FieldElement> Polynomial<T> {
     }
 
     pub fn add(&mut self, poly: &Polynomial<T>) -> &Self {
+        self.degree.set(None);
         for i in 0..self.coefs().len() {
             if i >= poly.coefs().len() {
                 break;
@@ -92,6 +117,7 @@ impl<T: FieldElement> Polynomial<T> {
     }
 
     pub fn sub(&mut self, poly: &Polynomial<T>) -> &Self {
+        self.degree.set(None);
         for i in 0..self.coefs().len() {
             if i >= poly.coefs().len() {
                 break;
@@ -108,17 +134,31 @@ impl<T: FieldElement> Polynomial<T> {
     // with a coefficient of 1
     // e.g. multiplying by x^5
     pub fn shift_and_clone(&self, degree: u32) -> Self {
-        let degree_usize = usize::try_from(degree).unwrap();
-        let mut shifted_coefs = vec![self.field().zero(); degree_usize];
-        shifted_coefs.extend(self.coefs.clone());
-        let mut out = Polynomial::new(&self.field);
-        out.coefs = shifted_coefs;
+        let mut out = self.clone();
+        out.shift(usize::try_from(degree).unwrap());
         out
     }
 
+    // multiply by x^k in place
+    pub fn shift(&mut self, k: usize) -> &Self {
+        self.degree.set(None);
+        let mut shifted_coefs = vec![self.field().zero(); k];
+        shifted_coefs.append(&mut self.coefs);
+        self.coefs = shifted_coefs;
+        self
+    }
+
     pub fn mul(&mut self, poly: &Polynomial<T>) -> &Self {
+        self.degree.set(None);
+        if self.coefs.is_empty() || poly.coefs().is_empty() {
+            self.coefs = Vec::new();
+            return self;
+        }
         let mut out = Vec::new();
-        out.resize(self.coefs.len() + poly.coefs().len(), self.field().zero());
+        out.resize(
+            self.coefs.len() + poly.coefs().len() - 1,
+            self.field().zero(),
+        );
         for i in 0..poly.coefs().len() {
             // self.mul_term(&poly.coefs()[i], i);
             for j in 0..self.coefs.len() {
@@ -135,13 +175,71 @@ impl<T: FieldElement> Polynomial<T> {
     }
 
     pub fn mul_scalar(&mut self, v: &T) -> &Self {
+        self.degree.set(None);
         for i in 0..self.coefs.len() {
             self.coefs[i] = self.field.mul(v, &self.coefs[i]);
         }
         self
     }
 
+    // non-mutating versions of add, sub, mul, and mul_scalar
+
+    pub fn plus(&self, poly: &Polynomial<T>) -> Polynomial<T> {
+        let mut out = self.clone();
+        out.add(poly);
+        out
+    }
+
+    pub fn minus(&self, poly: &Polynomial<T>) -> Polynomial<T> {
+        let mut out = self.clone();
+        out.sub(poly);
+        out
+    }
+
+    pub fn times(&self, poly: &Polynomial<T>) -> Polynomial<T> {
+        let mut out = self.clone();
+        out.mul(poly);
+        out
+    }
+
+    pub fn times_scalar(&self, v: &T) -> Polynomial<T> {
+        let mut out = self.clone();
+        out.mul_scalar(v);
+        out
+    }
+
+    // panics if v is zero
+    pub fn div_scalar(&mut self, v: &T) -> &Self {
+        let v_inv = self.field.inv(v);
+        self.mul_scalar(&v_inv)
+    }
+
+    // zero for the zero polynomial
+    pub fn leading_coef(&self) -> T {
+        match self.coefs.get(self.degree()) {
+            Some(coef) => coef.clone(),
+            None => self.field.zero(),
+        }
+    }
+
+    // the zero polynomial is left unchanged
+    pub fn make_monic(&mut self) -> &Self {
+        if self.is_zero() {
+            return self;
+        }
+        let leading = self.leading_coef();
+        self.div_scalar(&leading)
+    }
+
+    pub fn negate(&mut self) -> &Self {
+        for i in 0..self.coefs.len() {
+            self.coefs[i] = self.field.neg(&self.coefs[i]);
+        }
+        self
+    }
+
     pub fn exp(&mut self, v: usize) -> &Self {
+        self.degree.set(None);
         let mut out = Polynomial::new(&self.field);
         out.term(&self.field().one(), 0);
         for _ in 0..v {
@@ -154,6 +252,7 @@ impl<T: FieldElement> Polynomial<T> {
     // if we're scaling the polynomial using a generator point or similar
     // we probably already have a list of the exponents laying around
     pub fn scale_precalc(&mut self, _v: &T, exps: &Vec<T>) -> &Self {
+        self.degree.set(None);
         self.coefs = self
             .coefs
             .iter()
@@ -164,6 +263,7 @@ impl<T: FieldElement> Polynomial<T> {
     }
 
     pub fn scale(&mut self, v: T) -> &Self {
+        self.degree.set(None);
         self.coefs = self
             .coefs
             .iter()
@@ -182,6 +282,7 @@ impl<T: FieldElement> Polynomial<T> {
 
     // compose `poly` into `this`
     pub fn compose(&mut self, poly: &Polynomial<T>) -> &Self {
+        self.degree.set(None);
         let mut out = Polynomial::new(&self.field);
         for (exp, coef) in self.coefs.iter().enumerate() {
             let mut p = poly.clone();
@@ -193,6 +294,35 @@ impl<T: FieldElement> Polynomial<T> {
         self
     }
 
+    // compose using horners method, one fft multiplication
+    // per coefficient instead of an exp per term
+    pub fn compose_fast(&self, poly: &Polynomial<T>) -> Polynomial<T> {
+        let mut out = Polynomial::new(&self.field);
+        for coef in self.coefs.iter().rev() {
+            out = Self::mul_fft(&out, poly, &self.field);
+            out.term(coef, 0);
+        }
+        out.trim();
+        out
+    }
+
+    // formal derivative, c_i * x^i becomes i * c_i * x^(i-1)
+    pub fn derivative(&self) -> Polynomial<T> {
+        let mut out = Polynomial::new(&self.field);
+        out.coefs = self
+            .coefs
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(exp, coef)| {
+                self.field
+                    .mul(&T::from_u32(exp as u32, self.field().p()), coef)
+            })
+            .collect();
+        out.trim();
+        out
+    }
+
     // trim trailing zero coefficient
     pub fn trim(&mut self) {
         let mut new_len = self.coefs.len();
@@ -223,6 +353,21 @@ impl<T: FieldElement> Polynomial<T> {
         out
     }
 
+    // powers should be [1, v, v^2, ...] with at least
+    // as many entries as there are coefficients
+    pub fn eval_with_powers(&self, powers: &[T]) -> T {
+        if powers.len() < self.coefs.len() {
+            panic!("not enough powers to evaluate polynomial");
+        }
+        let terms: Vec<T> = self
+            .coefs
+            .iter()
+            .zip(powers)
+            .map(|(coef, power)| self.field.mul(coef, power))
+            .collect();
+        self.field.sum(&terms)
+    }
+
     pub fn eval_batch(&self, vals: &Vec<T>) -> Vec<T> {
         vals.iter().map(|v| self.eval(v)).collect()
     }
@@ -260,12 +405,33 @@ impl<T: FieldElement> Polynomial<T> {
     }
 
     pub fn eval_batch_coset(&self, offset: &T, size: u32) -> Vec<T> {
-        let mut scaled = self.clone();
-        let offset_domain = self.field.domain(offset, size);
-        scaled.scale_precalc(offset, &offset_domain);
         let (generator, _) = self.field.generator_cache(&size);
-        let domain = self.field.domain(&generator, size);
-        Self::eval_fft(scaled.coefs(), &domain, &self.field)
+        self.eval_coset(offset, &generator, size)
+    }
+
+    // evaluate over offset * omega^i for i in 0..size, using
+    // the fft when size is a power of two
+    pub fn eval_coset(&self, offset: &T, omega: &T, size: u32) -> Vec<T> {
+        let len = usize::try_from(size).unwrap();
+        if size < 2 || !size.is_power_of_two() {
+            return self
+                .field
+                .power_series(omega, len)
+                .iter()
+                .map(|v| self.eval(&self.field.mul(offset, v)))
+                .collect();
+        }
+        // f(offset * x) evaluated over the subgroup generated by omega
+        // coefficients are reduced modulo x^size - 1 first
+        let mut coefs = vec![self.field.zero(); len];
+        let mut power = self.field.one();
+        for (i, coef) in self.coefs.iter().enumerate() {
+            let j = i % len;
+            coefs[j] = self.field.add(&coefs[j], &self.field.mul(coef, &power));
+            power = self.field.mul(&power, offset);
+        }
+        let subgroup = self.field.domain(omega, size);
+        Self::eval_fft(&coefs, &subgroup, &self.field)
     }
 
     pub fn eval_batch_batch_coset(
@@ -294,6 +460,22 @@ impl<T: FieldElement> Polynomial<T> {
         Self::eval_fft(self.coefs(), domain, &self.field)
     }
 
+    // Evaluate a polynomial over a coset offset * omega^i with a
+    // power of two size, the inverse of interpolate_fft
+    // domain[0] must be the offset and domain[1] offset * omega
+    pub fn evaluate_fft(&self, domain: &[T]) -> Vec<T> {
+        if domain.len() < 2 {
+            return domain.iter().map(|v| self.eval(v)).collect();
+        }
+        if !domain.len().is_power_of_two() {
+            panic!("domain size must be a power of two");
+        }
+        let size = u32::try_from(domain.len()).unwrap();
+        let offset = &domain[0];
+        let omega = self.field.div(&domain[1], offset);
+        self.eval_coset(offset, &omega, size)
+    }
+
     pub fn eval_fft_batch(
         polys: &Vec<Polynomial<T>>,
         domain: &Vec<T>,
@@ -322,7 +504,7 @@ impl<T: FieldElement> Polynomial<T> {
         out
     }
 
-    pub fn eval_fft(coefs: &Vec<T>, domain: &Vec<T>, field: &Rc<Field<T>>) -> Vec<T> {
+    pub fn eval_fft(coefs: &Vec<T>, domain: &Vec<T>, field: &Field<T>) -> Vec<T> {
         let mut out = vec![T::from_params(field.p()); domain.len()];
         Self::eval_fft_(coefs, domain, field, 1, 0, 0, domain.len() / 2, &mut out);
         out
@@ -401,7 +583,7 @@ impl<T: FieldElement> Polynomial<T> {
     pub fn eval_fft_(
         coefs: &Vec<T>,
         domain: &Vec<T>,
-        field: &Rc<Field<T>>,
+        field: &Field<T>,
         slice_len: usize,
         offset: usize,
         left_dest: usize,
@@ -488,12 +670,7 @@ impl<T: FieldElement> Polynomial<T> {
         let domain_inv = field.domain(&generator_inv, domain_size);
 
         let out = Self::eval_fft_inv(&x3, &domain_inv, field);
-        let mut p = Polynomial {
-            field: Rc::clone(field),
-            coefs: out,
-        };
-        p.trim();
-        p
+        Polynomial::from_coefs(out, field)
     }
 
     pub fn div_coset(
@@ -519,7 +696,7 @@ impl<T: FieldElement> Polynomial<T> {
         let mut order = size;
 
         while u32::try_from(degree).unwrap() < order >> 1 {
-            g = field.mul(&g, &g);
+            g = field.square(&g);
             order >>= 1;
         }
 
@@ -545,19 +722,36 @@ impl<T: FieldElement> Polynomial<T> {
             .collect();
 
         let scaled_coefs = Self::eval_fft_inv(&out, &domain_inv, field);
-        let mut scaled_poly = Polynomial::new(field);
-        for i in 0..(poly1.degree() - poly2.degree() + 1) {
-            scaled_poly.term(&scaled_coefs[i], u32::try_from(i).unwrap());
-        }
+        let quotient_len = poly1.degree() - poly2.degree() + 1;
+        let mut scaled_poly = Polynomial::from_coefs(scaled_coefs[0..quotient_len].to_vec(), field);
         let offset_inv = field.inv(offset);
         let offset_domain_inv = field.domain(&offset_inv, order);
         scaled_poly.scale_precalc(&offset_inv, &offset_domain_inv);
         scaled_poly
     }
 
+    // self^e mod modulus using square and multiply, intermediate
+    // values never exceed twice the degree of the modulus
+    pub fn pow_mod(&self, e: &T, modulus: &Polynomial<T>) -> Polynomial<T> {
+        let (_, base) = self.div_fast(modulus);
+        let mut out = Polynomial::new(&self.field);
+        out.term(&self.field.one(), 0);
+        let (_, mut out) = out.div_fast(modulus);
+        for b in e.to_bytes_le().iter().rev() {
+            for i in (0..8).rev() {
+                out = Self::mul_fft(&out, &out, &self.field).div_fast(modulus).1;
+                if (b >> i) & 1 == 1 {
+                    out = Self::mul_fft(&out, &base, &self.field).div_fast(modulus).1;
+                }
+            }
+        }
+        out
+    }
+
     // remove and return the largest non-zero coefficient
     // coef, exp
     pub fn pop_term(&mut self) -> (T, usize) {
+        self.degree.set(None);
         let zero = self.field().zero();
         for i in 0..self.coefs.len() {
             let index = (self.coefs.len() - 1) - i;
@@ -587,7 +781,7 @@ impl<T: FieldElement> Polynomial<T> {
         let divisor_term = dclone.pop_term();
         let divisor_term_inv = self.field.inv(&divisor_term.0);
         let mut inter = self.clone();
-        while inter.degree() >= divisor.degree() {
+        while !inter.is_zero() && inter.degree() >= divisor.degree() {
             let largest_term = inter.clone().pop_term();
             let new_coef = self.field.mul(&largest_term.0, &divisor_term_inv);
             let new_exp = largest_term.1 - divisor_term.1;
@@ -599,6 +793,112 @@ impl<T: FieldElement> Polynomial<T> {
         (q, inter)
     }
 
+    // the remainder of div without building the quotient
+    pub fn rem(&self, divisor: &Polynomial<T>) -> Polynomial<T> {
+        if divisor.is_zero() {
+            panic!("divide by zero");
+        }
+        let divisor_degree = divisor.degree();
+        let divisor_term_inv = self.field.inv(&divisor.coefs[divisor_degree]);
+        let mut inter = self.clone();
+        while !inter.is_zero() && inter.degree() >= divisor_degree {
+            let largest_term = inter.clone().pop_term();
+            let new_coef = self.field.mul(&largest_term.0, &divisor_term_inv);
+            let new_exp = largest_term.1 - divisor_degree;
+            let mut t = divisor.shift_and_clone(u32::try_from(new_exp).unwrap());
+            t.mul_scalar(&new_coef);
+            inter.sub(&t);
+        }
+        inter
+    }
+
+    // monic greatest common divisor using the euclidean algorithm
+    // zero only if both inputs are zero
+    pub fn gcd(a: &Polynomial<T>, b: &Polynomial<T>, field: &Rc<Field<T>>) -> Polynomial<T> {
+        let mut a = Polynomial::from_coefs(a.coefs().clone(), field);
+        let mut b = Polynomial::from_coefs(b.coefs().clone(), field);
+        while !b.is_zero() {
+            let r = a.rem(&b);
+            a = b;
+            b = r;
+        }
+        a.trim();
+        a.make_monic();
+        a
+    }
+
+    // divide using a newton iteration inverse of the reversed divisor
+    // rev(q) = rev(self) * rev(divisor)^-1 mod x^(n - m + 1)
+    // https://en.wikipedia.org/wiki/Polynomial_long_division#Reversal
+    pub fn div_fast(&self, divisor: &Polynomial<T>) -> (Polynomial<T>, Polynomial<T>) {
+        if divisor.is_zero() {
+            panic!("divide by zero");
+        }
+        if self.is_zero() || self.degree() < divisor.degree() {
+            return (Polynomial::new(&self.field), self.clone());
+        }
+        let n = self.degree();
+        let m = divisor.degree();
+        if m < 16 || n - m < 16 {
+            return self.div(divisor);
+        }
+        let q_len = n - m + 1;
+        let rev_self: Vec<T> = self.coefs[0..=n].iter().rev().cloned().collect();
+        let rev_divisor: Vec<T> = divisor.coefs[0..=m].iter().rev().cloned().collect();
+        let rev_divisor_inv = Self::inv_series(&rev_divisor, q_len, &self.field);
+
+        let rev_q = Self::mul_fft(
+            &Polynomial::from_coefs(rev_self[0..q_len].to_vec(), &self.field),
+            &Polynomial::from_coefs(rev_divisor_inv, &self.field),
+            &self.field,
+        );
+        let mut q_coefs = rev_q.truncated_coefs(q_len);
+        q_coefs.reverse();
+        let q = Polynomial::from_coefs(q_coefs, &self.field);
+
+        let mut r = self.clone();
+        r.sub(&Self::mul_fft(&q, divisor, &self.field));
+        r.trim();
+        (q, r)
+    }
+
+    // power series inverse of f modulo x^len, f[0] must be non-zero
+    // g = g * (2 - f * g) doubles the precision each step
+    fn inv_series(f: &[T], len: usize, field: &Rc<Field<T>>) -> Vec<T> {
+        let mut g = vec![field.inv(&f[0])];
+        let mut precision = 1;
+        while precision < len {
+            precision = std::cmp::min(2 * precision, len);
+            let f_poly =
+                Polynomial::from_coefs(f[0..std::cmp::min(precision, f.len())].to_vec(), field);
+            let g_poly = Polynomial::from_coefs(g, field);
+            let fg = Self::mul_fft(&f_poly, &g_poly, field).truncated_coefs(precision);
+            let mut correction = Polynomial::from_coefs(fg, field);
+            correction.negate();
+            correction.term(&field.two(), 0);
+            g = Self::mul_fft(&g_poly, &correction, field).truncated_coefs(precision);
+        }
+        g
+    }
+
+    // the first len coefficients, zero padded
+    fn truncated_coefs(&self, len: usize) -> Vec<T> {
+        let mut out = self.coefs.clone();
+        out.resize(len, self.field.zero());
+        out
+    }
+
+    pub fn newton_interpolate(x_vals: &[T], y_vals: &[T], field: &Rc<Field<T>>) -> Polynomial<T> {
+        if x_vals.len() != y_vals.len() {
+            panic!("newton mismatch x/y array length");
+        }
+        let mut interpolant = NewtonInterpolant::new(field);
+        for (x, y) in x_vals.iter().zip(y_vals) {
+            interpolant.add_point(x, y);
+        }
+        interpolant.polynomial().clone()
+    }
+
     pub fn lagrange(x_vals: &Vec<T>, y_vals: &Vec<T>, field: &Rc<Field<T>>) -> Polynomial<T> {
         if x_vals.len() != y_vals.len() {
             panic!("lagrange mismatch x/y array length");
@@ -611,7 +911,7 @@ impl<T: FieldElement> Polynomial<T> {
             poly.term(&field.neg(v), 0);
             numerator.mul(&poly);
         }
-        let mut polynomials = Vec::new();
+        let mut denominators = Vec::new();
         for i in 0..x_vals.len() {
             let mut denominator = field.one();
             for j in 0..x_vals.len() {
@@ -620,11 +920,16 @@ impl<T: FieldElement> Polynomial<T> {
                 }
                 denominator = field.mul(&denominator, &x_vals[i].sub(&x_vals[j]));
             }
+            denominators.push(denominator);
+        }
+        let denominators_inv = field.batch_inv(&denominators);
+        let mut polynomials = Vec::new();
+        for i in 0..x_vals.len() {
             let mut n = Polynomial::new(field);
             n.term(&field.bigint(1), 1);
             n.term(&field.neg(&x_vals[i]), 0);
-            n.mul_scalar(&denominator);
-            let poly = numerator.safe_div(&n);
+            let mut poly = numerator.safe_div(&n);
+            poly.mul_scalar(&denominators_inv[i]);
             polynomials.push(poly);
         }
         let mut out = Polynomial::new(field);
@@ -634,6 +939,11 @@ impl<T: FieldElement> Polynomial<T> {
         out
     }
 
+    // interpolate the polynomial of lowest degree passing through
+    // (x_vals[i], y_vals[i]). x_vals are usually a coset of a power
+    // of two subgroup (offset * omega^i) but any distinct points work,
+    // the points are split in half recursively and recombined
+    // using zeroifiers
     pub fn interpolate_fft(
         x_vals: &Vec<T>,
         y_vals: &Vec<T>,
@@ -761,6 +1071,15 @@ impl<T: FieldElement> Polynomial<T> {
         y_vals_arr: &[Vec<T>],
         field: &Rc<Field<T>>,
     ) -> bool {
+        Self::find_non_colinear(x_vals_arr, y_vals_arr, field).is_none()
+    }
+
+    // index of the first set of points that isn't colinear
+    pub fn find_non_colinear(
+        x_vals_arr: &[Vec<T>],
+        y_vals_arr: &[Vec<T>],
+        field: &Rc<Field<T>>,
+    ) -> Option<usize> {
         let mut to_inv = Vec::new();
         for x_vals in x_vals_arr {
             to_inv.push(field.sub(&x_vals[1], &x_vals[0]));
@@ -775,10 +1094,10 @@ impl<T: FieldElement> Polynomial<T> {
             let slope_1 = field.mul(&y_diff_1, x_diff_inv_1);
             let slope_2 = field.mul(&y_diff_2, x_diff_inv_2);
             if slope_1 != slope_2 {
-                return false;
+                return Some(i);
             }
         }
-        true
+        None
     }
 
     pub fn test_colinearity(x_vals: &Vec<T>, y_vals: &Vec<T>, field: &Rc<Field<T>>) -> bool {
@@ -827,6 +1146,185 @@ impl<T: FieldElement> Polynomial<T> {
     }
 }
 
+// interpolant in newton form that can be extended one point
+// at a time using divided differences
+// https://en.wikipedia.org/wiki/Newton_polynomial
+pub struct NewtonInterpolant<T: FieldElement> {
+    field: Rc<Field<T>>,
+    x_vals: Vec<T>,
+    // row[j] = f[x_(n-j), ..., x_n] for the latest point x_n
+    row: Vec<T>,
+    // (x - x_0) * ... * (x - x_n)
+    basis: Polynomial<T>,
+    poly: Polynomial<T>,
+}
+
+impl<T: FieldElement> NewtonInterpolant<T> {
+    pub fn new(field: &Rc<Field<T>>) -> NewtonInterpolant<T> {
+        let mut basis = Polynomial::new(field);
+        basis.term(&field.one(), 0);
+        NewtonInterpolant {
+            field: Rc::clone(field),
+            x_vals: Vec::new(),
+            row: Vec::new(),
+            basis,
+            poly: Polynomial::new(field),
+        }
+    }
+
+    // O(n) in the number of existing points
+    pub fn add_point(&mut self, x: &T, y: &T) {
+        let n = self.x_vals.len();
+        let mut row = Vec::with_capacity(n + 1);
+        row.push(y.clone());
+        for j in 1..=n {
+            let x_diff = self.field.sub(x, &self.x_vals[n - j]);
+            if x_diff == self.field.zero() {
+                panic!("newton interpolation points must be distinct");
+            }
+            let y_diff = self.field.sub(&row[j - 1], &self.row[j - 1]);
+            row.push(self.field.div(&y_diff, &x_diff));
+        }
+        let mut term = self.basis.clone();
+        term.mul_scalar(&row[n]);
+        self.poly.add(&term);
+
+        let mut linear = Polynomial::new(&self.field);
+        linear.term(&self.field.neg(x), 0);
+        linear.term(&self.field.one(), 1);
+        self.basis.mul(&linear);
+
+        self.x_vals.push(x.clone());
+        self.row = row;
+    }
+
+    pub fn polynomial(&self) -> &Polynomial<T> {
+        &self.poly
+    }
+}
+
+// only the non-zero terms, keyed by exponent
+#[derive(Clone, Debug)]
+pub struct SparsePolynomial<T: FieldElement> {
+    field: Rc<Field<T>>,
+    terms: BTreeMap<usize, T>,
+}
+
+impl<T: FieldElement> SparsePolynomial<T> {
+    pub fn new(f: &Rc<Field<T>>) -> SparsePolynomial<T> {
+        SparsePolynomial {
+            field: Rc::clone(f),
+            terms: BTreeMap::new(),
+        }
+    }
+
+    pub fn from_dense(poly: &Polynomial<T>) -> SparsePolynomial<T> {
+        let mut out = SparsePolynomial::new(poly.field());
+        for (exp, coef) in poly.coefs().iter().enumerate() {
+            out.term(coef, exp);
+        }
+        out
+    }
+
+    pub fn to_dense(&self) -> Polynomial<T> {
+        let mut coefs = vec![self.field.zero(); self.degree() + 1];
+        for (exp, coef) in &self.terms {
+            coefs[*exp] = coef.clone();
+        }
+        Polynomial::from_coefs(coefs, &self.field)
+    }
+
+    pub fn terms(&self) -> &BTreeMap<usize, T> {
+        &self.terms
+    }
+
+    pub fn degree(&self) -> usize {
+        match self.terms.keys().next_back() {
+            Some(exp) => *exp,
+            None => 0,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn term(&mut self, coef: &T, exp: usize) -> &Self {
+        let zero = self.field.zero();
+        let v = match self.terms.get(&exp) {
+            Some(existing) => self.field.add(existing, coef),
+            None => coef.clone(),
+        };
+        if v == zero {
+            self.terms.remove(&exp);
+        } else {
+            self.terms.insert(exp, v);
+        }
+        self
+    }
+
+    pub fn add(&mut self, poly: &SparsePolynomial<T>) -> &Self {
+        for (exp, coef) in &poly.terms {
+            self.term(coef, *exp);
+        }
+        self
+    }
+
+    pub fn mul(&mut self, poly: &SparsePolynomial<T>) -> &Self {
+        let mut out = SparsePolynomial::new(&self.field);
+        for (exp1, coef1) in &self.terms {
+            for (exp2, coef2) in &poly.terms {
+                out.term(&self.field.mul(coef1, coef2), exp1 + exp2);
+            }
+        }
+        self.terms = out.terms;
+        self
+    }
+
+    // walk the terms in order so each power is built from the previous one
+    pub fn eval(&self, v: &T) -> T {
+        let mut out = self.field.zero();
+        let mut power = self.field.one();
+        let mut last_exp = 0;
+        for (exp, coef) in &self.terms {
+            let gap = u32::try_from(exp - last_exp).unwrap();
+            if gap > 0 {
+                let gap_power = self.field.exp(v, &T::from_u32(gap, self.field.p()));
+                power = self.field.mul(&power, &gap_power);
+            }
+            last_exp = *exp;
+            out = self.field.add(&out, &self.field.mul(coef, &power));
+        }
+        out
+    }
+}
+
+// e.g. 4x^2 + 2x + 99, coefficients in canonical form
+impl<T: FieldElement> fmt::Display for Polynomial<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let zero = self.field.zero();
+        let one = self.field.one();
+        let mut terms = Vec::new();
+        for (exp, coef) in self.coefs.iter().enumerate().rev() {
+            if coef == &zero {
+                continue;
+            }
+            let coef_str = bytes_le_to_dec(&coef.to_bytes_le());
+            terms.push(match exp {
+                0 => coef_str,
+                1 if coef == &one => "x".to_string(),
+                1 => format!("{}x", coef_str),
+                _ if coef == &one => format!("x^{}", exp),
+                _ => format!("{}x^{}", coef_str, exp),
+            });
+        }
+        if terms.is_empty() {
+            return write!(f, "0");
+        }
+        write!(f, "{}", terms.join(" + "))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::field_element::{ParamWrapper, CryptoBigIntElement, UC};
@@ -851,10 +1349,21 @@ mod tests {
         }
         assert!(Polynomial::test_colinearity(&x_vals, &y_vals, &f));
         assert!(Polynomial::test_colinearity_batch(
-            &vec!(x_vals),
-            &vec!(y_vals),
+            &vec!(x_vals.clone()),
+            &vec!(y_vals.clone()),
             &f
         ));
+
+        let mut bad_y_vals = y_vals.clone();
+        bad_y_vals[2] = f.add(&bad_y_vals[2], &f.one());
+        assert_eq!(
+            Polynomial::find_non_colinear(
+                &[x_vals.clone(), x_vals.clone(), x_vals],
+                &[y_vals.clone(), bad_y_vals, y_vals],
+                &f
+            ),
+            Some(1)
+        );
     }
 
     #[test]
@@ -1230,4 +1739,602 @@ mod tests {
 
         assert!(zeroifier.is_equal(&zeroifier_fft));
     }
+
+    #[test]
+    fn should_interpolate_and_evaluate_coset() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        // 3x^3 + 7x^2 - 2x + 11
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(11), 0);
+        poly.term(&f.bigint(-2), 1);
+        poly.term(&f.bigint(7), 2);
+        poly.term(&f.bigint(3), 3);
+
+        let coset = f.coset(8, f.g());
+        let values = poly.evaluate_fft(&coset);
+        for i in 0..coset.len() {
+            assert_eq!(values[i], poly.eval(&coset[i]));
+        }
+
+        let mut interpolated = Polynomial::interpolate_fft(&coset, &values, &f);
+        interpolated.trim();
+        assert_eq!(interpolated.coefs(), poly.coefs());
+
+        // degree larger than the domain
+        let mut large = poly.clone();
+        large.term(&f.bigint(9), 12);
+        let values = large.evaluate_fft(&coset);
+        for i in 0..coset.len() {
+            assert_eq!(values[i], large.eval(&coset[i]));
+        }
+    }
+
+    #[test]
+    fn should_eval_horner_like_naive() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        for degree in 0..20 {
+            let mut poly = Polynomial::new(&f);
+            for i in 0..degree {
+                poly.term(&f.random(), i);
+            }
+            let v = f.random();
+            // sum of coef * v^i
+            let mut expected = f.zero();
+            for (i, coef) in poly.coefs().iter().enumerate() {
+                let term = f.mul(coef, &f.exp(&v, &f.bigint(i as i32)));
+                expected = f.add(&expected, &term);
+            }
+            assert_eq!(poly.eval(&v), expected);
+        }
+    }
+
+    #[test]
+    fn should_trim_trailing_zeroes() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(3), 0);
+        poly.term(&f.bigint(4), 2);
+        poly.term(&f.zero(), 5);
+        assert_eq!(poly.coefs().len(), 6);
+        poly.trim();
+        assert_eq!(poly.coefs().len(), 3);
+        assert_eq!(poly.degree(), 2);
+
+        let mut zero = Polynomial::new(&f);
+        zero.term(&f.zero(), 4);
+        zero.trim();
+        assert_eq!(zero.coefs().len(), 0);
+
+        // (x + 1) * (x + 2) should have exactly 3 coefficients
+        let mut a = Polynomial::new(&f);
+        a.term(&f.one(), 0);
+        a.term(&f.one(), 1);
+        let mut b = Polynomial::new(&f);
+        b.term(&f.bigint(2), 0);
+        b.term(&f.one(), 1);
+        a.mul(&b);
+        assert_eq!(a.coefs().len(), 3);
+    }
+
+    #[test]
+    fn should_vanish_on_zeroifier_roots() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let roots: Vec<CryptoBigIntElement> = (0..37).map(|_| f.random()).collect();
+        let zeroifier = Polynomial::zeroifier_fft(&roots, &f);
+        assert_eq!(zeroifier.degree(), roots.len());
+        for root in &roots {
+            assert_eq!(zeroifier.eval(root), f.zero());
+        }
+        let mut v = f.random();
+        while roots.contains(&v) {
+            v = f.random();
+        }
+        assert_ne!(zeroifier.eval(&v), f.zero());
+    }
+
+    #[test]
+    fn should_eval_batch_fast_random_points() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut poly = Polynomial::new(&f);
+        for i in 0..50 {
+            poly.term(&f.random(), i);
+        }
+        let points: Vec<CryptoBigIntElement> = (0..64).map(|_| f.random()).collect();
+        assert_eq!(poly.eval_batch_fast(&points), poly.eval_batch(&points));
+    }
+
+    #[test]
+    fn should_take_derivative() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        // x^3 + 2x^2 + 5
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(5), 0);
+        poly.term(&f.bigint(2), 2);
+        poly.term(&f.bigint(1), 3);
+
+        // 3x^2 + 4x
+        let mut expected = Polynomial::new(&f);
+        expected.term(&f.bigint(4), 1);
+        expected.term(&f.bigint(3), 2);
+        assert!(poly.derivative().is_equal(&expected));
+
+        let mut constant = Polynomial::new(&f);
+        constant.term(&f.bigint(7), 0);
+        assert!(constant.derivative().is_zero());
+        assert!(Polynomial::new(&f).derivative().is_zero());
+    }
+
+    #[test]
+    fn should_build_from_coefs() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut expected = Polynomial::new(&f);
+        expected.term(&f.bigint(99), 0);
+        expected.term(&f.bigint(-2), 1);
+        expected.term(&f.bigint(4), 3);
+
+        let poly = Polynomial::from_coefs(
+            vec![f.bigint(99), f.bigint(-2), f.zero(), f.bigint(4), f.zero()],
+            &f,
+        );
+        assert!(poly.is_equal(&expected));
+        assert_eq!(poly.coefs(), expected.coefs());
+    }
+
+    #[test]
+    fn should_negate_polynomial() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut poly = Polynomial::new(&f);
+        for i in 0..10 {
+            poly.term(&f.random(), i);
+        }
+        let mut negated = poly.clone();
+        negated.negate();
+        assert!(negated.clone().add(&poly).is_zero());
+
+        let mut expected = Polynomial::new(&f);
+        expected.sub(&poly);
+        assert!(negated.is_equal(&expected));
+    }
+
+    #[test]
+    fn should_div_fast_like_div() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let degrees = [(10, 3), (40, 0), (100, 20), (150, 70), (64, 63)];
+        for (dividend_degree, divisor_degree) in degrees {
+            let mut dividend = Polynomial::new(&f);
+            for i in 0..=dividend_degree {
+                dividend.term(&f.random(), i);
+            }
+            let mut divisor = Polynomial::new(&f);
+            for i in 0..=divisor_degree {
+                divisor.term(&f.random(), i);
+            }
+            let (q_fast, r_fast) = dividend.div_fast(&divisor);
+            let (q, r) = dividend.div(&divisor);
+            assert!(q_fast.is_equal(&q));
+            assert!(r_fast.is_equal(&r));
+        }
+    }
+
+    #[test]
+    fn should_div_scalar() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut poly = Polynomial::new(&f);
+        for i in 0..10 {
+            poly.term(&f.random(), i);
+        }
+        let k = f.bigint(12345);
+        let mut scaled = poly.clone();
+        scaled.mul_scalar(&k);
+        assert!(!scaled.is_equal(&poly));
+        assert!(scaled.div_scalar(&k).is_equal(&poly));
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_fail_to_div_scalar_by_zero() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(1), 0);
+        poly.div_scalar(&f.zero());
+    }
+
+    #[test]
+    fn should_display_polynomial() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(99), 0);
+        poly.term(&f.bigint(2), 1);
+        poly.term(&f.bigint(4), 2);
+        poly.term(&f.bigint(1), 5);
+        assert_eq!(poly.to_string(), "x^5 + 4x^2 + 2x + 99");
+
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(-1), 1);
+        assert_eq!(poly.to_string(), "3221225472x");
+        assert_eq!(Polynomial::new(&f).to_string(), "0");
+    }
+
+    #[test]
+    fn should_cache_degree() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+        let fresh_degree = |poly: &Polynomial<CryptoBigIntElement>| poly.compute_degree();
+
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(3), 2);
+        assert_eq!(poly.degree(), 2);
+        poly.term(&f.bigint(1), 7);
+        assert_eq!(poly.degree(), fresh_degree(&poly));
+        let mut other = Polynomial::new(&f);
+        other.term(&f.bigint(-1), 7);
+        poly.add(&other);
+        assert_eq!(poly.degree(), 2);
+        poly.sub(&other);
+        assert_eq!(poly.degree(), 7);
+        poly.mul(&other);
+        assert_eq!(poly.degree(), fresh_degree(&poly));
+        poly.pop_term();
+        assert_eq!(poly.degree(), fresh_degree(&poly));
+        poly.mul_scalar(&f.zero());
+        assert_eq!(poly.degree(), 0);
+        poly.trim();
+        assert_eq!(poly.degree(), fresh_degree(&poly));
+    }
+
+    #[test]
+    fn should_compare_different_degrees() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut a = Polynomial::new(&f);
+        a.term(&f.bigint(1), 0);
+        a.term(&f.bigint(2), 9);
+        let mut b = Polynomial::new(&f);
+        b.term(&f.bigint(1), 0);
+        b.term(&f.bigint(2), 3);
+        assert!(!a.is_equal(&b));
+        assert!(!b.is_equal(&a));
+
+        // trailing zeros don't change the degree
+        let mut c = b.clone();
+        c.term(&f.zero(), 20);
+        assert!(b.is_equal(&c));
+        assert!(c.is_equal(&b));
+
+        let mut zero = Polynomial::new(&f);
+        zero.term(&f.zero(), 0);
+        assert!(zero.is_equal(&Polynomial::new(&f)));
+    }
+
+    #[test]
+    fn should_interpolate_newton() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let size = 32;
+        let gen = f.generator(f.bigint(size));
+        let mut x_vals = Vec::new();
+        let mut y_vals = Vec::new();
+        for i in 0..size {
+            x_vals.push(f.exp(&gen, &f.bigint(i)));
+            y_vals.push(f.random());
+        }
+
+        let newton = Polynomial::newton_interpolate(&x_vals, &y_vals, &f);
+        let lagrange = Polynomial::lagrange(&x_vals, &y_vals, &f);
+        assert!(newton.is_equal(&lagrange));
+
+        // extending by a point matches interpolating from scratch
+        let mut interpolant = NewtonInterpolant::new(&f);
+        for i in 0..x_vals.len() {
+            interpolant.add_point(&x_vals[i], &y_vals[i]);
+        }
+        let x = f.random();
+        let y = f.random();
+        interpolant.add_point(&x, &y);
+        x_vals.push(x);
+        y_vals.push(y);
+        let lagrange = Polynomial::lagrange(&x_vals, &y_vals, &f);
+        assert!(interpolant.polynomial().is_equal(&lagrange));
+    }
+
+    #[test]
+    fn should_pow_mod() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut modulus = Polynomial::new(&f);
+        for i in 0..6 {
+            modulus.term(&f.random(), i);
+        }
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(3), 0);
+        poly.term(&f.bigint(1), 1);
+        poly.term(&f.bigint(7), 3);
+        for e in [0, 1, 2, 5, 13] {
+            let mut expected = poly.clone();
+            expected.exp(e);
+            let (_, expected) = expected.div(&modulus);
+            let out = poly.pow_mod(&f.bigint(e as i32), &modulus);
+            assert!(out.is_equal(&expected));
+        }
+    }
+
+    #[test]
+    fn should_convert_sparse_polynomial() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut dense = Polynomial::new(&f);
+        dense.term(&f.bigint(4), 0);
+        dense.term(&f.bigint(-3), 17);
+        dense.term(&f.bigint(9), 100);
+        let sparse = SparsePolynomial::from_dense(&dense);
+        assert_eq!(sparse.terms().len(), 3);
+        assert_eq!(sparse.degree(), 100);
+        assert!(sparse.to_dense().is_equal(&dense));
+        assert_eq!(sparse.to_dense().coefs(), dense.coefs());
+
+        let empty = SparsePolynomial::from_dense(&Polynomial::new(&f));
+        assert!(empty.is_zero());
+        assert!(empty.to_dense().is_zero());
+    }
+
+    #[test]
+    fn should_match_sparse_and_dense_arithmetic() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut dense1 = Polynomial::new(&f);
+        let mut dense2 = Polynomial::new(&f);
+        for i in [0, 3, 40, 41] {
+            dense1.term(&f.random(), i);
+        }
+        for i in [2, 9, 70] {
+            dense2.term(&f.random(), i);
+        }
+        let sparse1 = SparsePolynomial::from_dense(&dense1);
+        let sparse2 = SparsePolynomial::from_dense(&dense2);
+
+        let v = f.random();
+        assert_eq!(sparse1.eval(&v), dense1.eval(&v));
+
+        let mut sum = sparse1.clone();
+        sum.add(&sparse2);
+        assert!(sum.to_dense().is_equal(dense1.clone().add(&dense2)));
+
+        let mut product = sparse1.clone();
+        product.mul(&sparse2);
+        assert!(product.to_dense().is_equal(dense1.clone().mul(&dense2)));
+
+        // cancelling terms are removed
+        let mut negated = dense1.clone();
+        negated.negate();
+        let mut cancelled = sparse1.clone();
+        cancelled.add(&SparsePolynomial::from_dense(&negated));
+        assert!(cancelled.is_zero());
+    }
+
+    #[test]
+    fn should_shift_polynomial() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        // 2x + 1
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(1), 0);
+        poly.term(&f.bigint(2), 1);
+
+        // 2x^4 + x^3
+        let mut expected = Polynomial::new(&f);
+        expected.term(&f.bigint(1), 3);
+        expected.term(&f.bigint(2), 4);
+
+        assert!(poly.shift_and_clone(3).is_equal(&expected));
+        assert!(poly.shift(3).is_equal(&expected));
+        assert_eq!(poly.degree(), 4);
+    }
+
+    #[test]
+    fn should_make_monic() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        // 3x^2 + 6
+        let mut poly = Polynomial::new(&f);
+        poly.term(&f.bigint(6), 0);
+        poly.term(&f.bigint(3), 2);
+        assert_eq!(poly.leading_coef(), f.bigint(3));
+
+        // x^2 + 2
+        let mut expected = Polynomial::new(&f);
+        expected.term(&f.bigint(2), 0);
+        expected.term(&f.bigint(1), 2);
+        assert!(poly.make_monic().is_equal(&expected));
+        assert_eq!(poly.leading_coef(), f.one());
+
+        let mut zero = Polynomial::new(&f);
+        assert_eq!(zero.leading_coef(), f.zero());
+        assert!(zero.make_monic().is_zero());
+    }
+
+    #[test]
+    fn should_eval_with_powers() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut poly = Polynomial::new(&f);
+        for i in 0..30 {
+            poly.term(&f.random(), i);
+        }
+        let v = f.random();
+        let powers = f.power_series(&v, poly.degree() + 1);
+        assert_eq!(powers.len(), 30);
+        assert_eq!(powers[2], f.mul(&v, &v));
+        assert_eq!(poly.eval_with_powers(&powers), poly.eval(&v));
+    }
+
+    #[test]
+    fn should_rem_like_div() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        for (dividend_degree, divisor_degree) in [(10, 3), (20, 0), (31, 17)] {
+            let mut dividend = Polynomial::new(&f);
+            for i in 0..=dividend_degree {
+                dividend.term(&f.random(), i);
+            }
+            let mut divisor = Polynomial::new(&f);
+            for i in 0..=divisor_degree {
+                divisor.term(&f.random(), i);
+            }
+            let (_, r) = dividend.div(&divisor);
+            assert!(dividend.rem(&divisor).is_equal(&r));
+            // a higher degree divisor leaves self as the remainder
+            assert!(divisor.rem(&dividend).is_equal(&divisor));
+        }
+    }
+
+    #[test]
+    fn should_find_gcd() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        // x^2 - 1
+        let mut a = Polynomial::new(&f);
+        a.term(&f.bigint(-1), 0);
+        a.term(&f.bigint(1), 2);
+        // 3x - 3
+        let mut b = Polynomial::new(&f);
+        b.term(&f.bigint(-3), 0);
+        b.term(&f.bigint(3), 1);
+        // x - 1
+        let mut expected = Polynomial::new(&f);
+        expected.term(&f.bigint(-1), 0);
+        expected.term(&f.bigint(1), 1);
+        assert!(Polynomial::gcd(&a, &b, &f).is_equal(&expected));
+        assert!(Polynomial::gcd(&b, &a, &f).is_equal(&expected));
+
+        // x + 2 shares no root with x^2 - 1
+        let mut c = Polynomial::new(&f);
+        c.term(&f.bigint(2), 0);
+        c.term(&f.bigint(1), 1);
+        let coprime = Polynomial::gcd(&a, &c, &f);
+        assert_eq!(coprime.degree(), 0);
+        assert_eq!(coprime.leading_coef(), f.one());
+    }
+
+    #[test]
+    fn should_eval_coset() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut poly = Polynomial::new(&f);
+        for i in 0..20 {
+            poly.term(&f.random(), i);
+        }
+        let offset = f.random();
+        for size in [1, 6, 16] {
+            let omega = f.generator(f.bigint(16));
+            let values = poly.eval_coset(&offset, &omega, size);
+            assert_eq!(values.len(), usize::try_from(size).unwrap());
+            for (i, v) in values.iter().enumerate() {
+                let x = f.mul(&offset, &f.exp(&omega, &f.bigint(i as i32)));
+                assert_eq!(v, &poly.eval(&x));
+            }
+        }
+    }
+
+    #[test]
+    fn should_not_mutate_in_functional_arithmetic() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        let mut a = Polynomial::new(&f);
+        let mut b = Polynomial::new(&f);
+        for i in 0..8 {
+            a.term(&f.random(), i);
+            b.term(&f.random(), i + 2);
+        }
+        let a_before = a.clone();
+        let b_before = b.clone();
+        let k = f.random();
+
+        assert!(a.plus(&b).is_equal(a.clone().add(&b)));
+        assert!(a.minus(&b).is_equal(a.clone().sub(&b)));
+        assert!(a.times(&b).is_equal(a.clone().mul(&b)));
+        assert!(a.times_scalar(&k).is_equal(a.clone().mul_scalar(&k)));
+        assert_eq!(a.coefs(), a_before.coefs());
+        assert_eq!(b.coefs(), b_before.coefs());
+    }
+
+    #[test]
+    fn should_compose_fast_like_compose() {
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let g = CryptoBigIntElement::from_u32(5, &p);
+        let f = Rc::new(Field::new(g));
+
+        for (outer_degree, inner_degree) in [(0, 3), (3, 0), (4, 5), (7, 6)] {
+            let mut outer = Polynomial::new(&f);
+            for i in 0..=outer_degree {
+                outer.term(&f.random(), i);
+            }
+            let mut inner = Polynomial::new(&f);
+            for i in 0..=inner_degree {
+                inner.term(&f.random(), i);
+            }
+            let fast = outer.compose_fast(&inner);
+            assert!(fast.is_equal(outer.clone().compose(&inner)));
+        }
+    }
 }
@@ -1,15 +1,385 @@
-use crate::channel::Channel;
+use crate::channel::{Channel, ChannelHasher};
 use crate::field::Field;
-use crate::fri::{Fri, FriOptions};
+use crate::fri::{read_u32, read_word, read_words, write_words};
+use crate::fri::{Fri, FriError, FriOptions, FriProof};
 use crate::mpolynomial::MPolynomial;
 use crate::polynomial::Polynomial;
-use crate::tree::Tree;
+use crate::tree::{Blake3Hasher, Tree, TreeError, TreeHasher};
 use crate::field_element::{FieldElement};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
 use std::rc::Rc;
 
-pub struct Stark<T: FieldElement> {
+// labels separating the stark commitments from each other and
+// from the fri messages after them
+const BOUNDARY_ROOT_LABEL: &str = "stark_boundary_root";
+const RANDOMIZER_ROOT_LABEL: &str = "stark_randomizer_root";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StarkError {
+    // a trace row doesn't have one value per register
+    InconsistentTrace {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    // a trace column isn't as long as the first column
+    InconsistentColumns {
+        column: usize,
+        len: usize,
+        expected: usize,
+    },
+    // the trace isn't the length the stark was built for
+    TraceLength {
+        len: usize,
+        expected: usize,
+    },
+    // a boundary constraint references a register or cycle
+    // outside the trace
+    InvalidBoundary {
+        cycle: u32,
+        register: u32,
+    },
+    // two boundary constraints give the same register at the
+    // same cycle different values
+    ConflictingBoundary {
+        cycle: u32,
+        register: u32,
+    },
+    // a periodic column's length isn't a power of two no longer
+    // than the omicron domain
+    InvalidPeriodicColumn {
+        column: usize,
+        len: usize,
+    },
+    // the coset offset is zero or inside the evaluation subgroup,
+    // so the coset isn't disjoint from the trace domain
+    InvalidOffset,
+    // the fri domain for a trace this long overflows a u32 or
+    // is larger than any subgroup of the field
+    TraceTooLong {
+        len: u32,
+    },
+    // the config can't be folded by fri
+    InvalidConfig {
+        reason: &'static str,
+    },
+    // no transition constraints were given, the random linear
+    // combination needs at least one
+    NoTransitionConstraints,
+    // a transition constraint uses more variables than the
+    // cycle index, two states and periodic columns provide
+    ConstraintVariables {
+        count: usize,
+        max: usize,
+    },
+    // a transition constraint's quotient has too high a degree
+    // for the evaluation domain
+    ConstraintDegree {
+        constraint: usize,
+        degree: u32,
+        max: u32,
+    },
+    // a transition constraint doesn't vanish between two
+    // consecutive trace rows
+    UnsatisfiedConstraint {
+        constraint: usize,
+        row: usize,
+    },
+    // a trace value doesn't match the boundary constraint on it
+    UnsatisfiedBoundary {
+        cycle: u32,
+        register: u32,
+    },
+    // the proof isn't a serialized channel of the expected shape
+    MalformedProof,
+    // the fri proof of the combined codeword is invalid
+    Fri(FriError),
+    // a boundary quotient opening doesn't authenticate
+    // against its root
+    InvalidBoundaryPath {
+        register: u32,
+        error: TreeError,
+    },
+    // a randomizer opening doesn't authenticate against its root
+    InvalidRandomizerPath {
+        error: TreeError,
+    },
+    // the openings don't combine to the value fri proved
+    CombinationMismatch {
+        index: u32,
+    },
+}
+
+impl fmt::Display for StarkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StarkError::InconsistentTrace { row, len, expected } => write!(
+                f,
+                "trace row {} has {} registers but {} were expected",
+                row, len, expected
+            ),
+            StarkError::InconsistentColumns {
+                column,
+                len,
+                expected,
+            } => write!(
+                f,
+                "trace column {} has length {} but {} was expected",
+                column, len, expected
+            ),
+            StarkError::TraceLength { len, expected } => {
+                write!(f, "trace has length {} but {} was expected", len, expected)
+            }
+            StarkError::InvalidBoundary { cycle, register } => write!(
+                f,
+                "boundary constraint at cycle {} register {} is outside the trace",
+                cycle, register
+            ),
+            StarkError::ConflictingBoundary { cycle, register } => write!(
+                f,
+                "conflicting boundary constraints at cycle {} register {}",
+                cycle, register
+            ),
+            StarkError::InvalidPeriodicColumn { column, len } => write!(
+                f,
+                "periodic column {} has length {} which is not a power of two within the domain",
+                column, len
+            ),
+            StarkError::InvalidOffset => write!(
+                f,
+                "offset must be nonzero and outside the evaluation subgroup"
+            ),
+            StarkError::TraceTooLong { len } => write!(
+                f,
+                "a trace of length {} is too long for the fri domain",
+                len
+            ),
+            StarkError::InvalidConfig { reason } => write!(f, "invalid stark config: {}", reason),
+            StarkError::NoTransitionConstraints => {
+                write!(f, "at least one transition constraint is required")
+            }
+            StarkError::ConstraintVariables { count, max } => write!(
+                f,
+                "transition constraint uses {} variables but at most {} are available",
+                count, max
+            ),
+            StarkError::ConstraintDegree {
+                constraint,
+                degree,
+                max,
+            } => write!(
+                f,
+                "transition constraint {} has quotient degree {} but at most {} is supported",
+                constraint, degree, max
+            ),
+            StarkError::UnsatisfiedConstraint { constraint, row } => write!(
+                f,
+                "transition constraint {} is not satisfied at trace row {}",
+                constraint, row
+            ),
+            StarkError::UnsatisfiedBoundary { cycle, register } => write!(
+                f,
+                "boundary constraint at cycle {} register {} is not satisfied",
+                cycle, register
+            ),
+            StarkError::MalformedProof => write!(f, "malformed stark proof"),
+            StarkError::Fri(error) => write!(f, "fri verification failed: {}", error),
+            StarkError::InvalidBoundaryPath { register, error } => write!(
+                f,
+                "invalid boundary quotient path for register {}: {}",
+                register, error
+            ),
+            StarkError::InvalidRandomizerPath { error } => {
+                write!(f, "invalid randomizer path: {}", error)
+            }
+            StarkError::CombinationMismatch { index } => {
+                write!(f, "invalid combination value at index {}", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StarkError {}
+
+// the first place a trace fails its constraints, as found by
+// Stark::check_trace
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstraintViolation {
+    // transition constraint `constraint` doesn't vanish between
+    // trace rows `row` and `row + 1`
+    Transition { row: usize, constraint: usize },
+    // boundary constraint `constraint` doesn't hold at trace
+    // row `row`
+    Boundary { row: usize, constraint: usize },
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstraintViolation::Transition { row, constraint } => write!(
+                f,
+                "transition constraint {} is not satisfied at trace row {}",
+                constraint, row
+            ),
+            ConstraintViolation::Boundary { row, constraint } => write!(
+                f,
+                "boundary constraint {} is not satisfied at trace row {}",
+                constraint, row
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConstraintViolation {}
+
+// one opened codeword value and its authentication path
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StarkOpening {
+    pub value: [u8; 32],
+    pub path: Vec<[u8; 32]>,
+}
+
+// everything a stark prover pushes to the channel, in a
+// binary form much smaller than the serialized channel
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StarkProof {
+    pub boundary_roots: Vec<[u8; 32]>,
+    pub randomizer_root: [u8; 32],
+    pub fri: FriProof,
+    // the openings of each boundary quotient codeword
+    pub boundary_openings: Vec<Vec<StarkOpening>>,
+    pub randomizer_openings: Vec<StarkOpening>,
+}
+
+fn write_openings(out: &mut Vec<u8>, openings: &[StarkOpening]) {
+    out.extend_from_slice(&u32::try_from(openings.len()).unwrap().to_le_bytes());
+    for opening in openings {
+        out.extend_from_slice(&opening.value);
+        write_words(out, &opening.path);
+    }
+}
+
+fn read_openings(bytes: &[u8], pos: &mut usize) -> Option<Vec<StarkOpening>> {
+    let len = read_u32(bytes, pos)?;
+    (0..len)
+        .map(|_| {
+            Some(StarkOpening {
+                value: read_word(bytes, pos)?,
+                path: read_words(bytes, pos)?,
+            })
+        })
+        .collect()
+}
+
+impl StarkProof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_words(&mut out, &self.boundary_roots);
+        out.extend_from_slice(&self.randomizer_root);
+        let fri = self.fri.to_bytes();
+        out.extend_from_slice(&u32::try_from(fri.len()).unwrap().to_le_bytes());
+        out.extend_from_slice(&fri);
+        out.extend_from_slice(
+            &u32::try_from(self.boundary_openings.len())
+                .unwrap()
+                .to_le_bytes(),
+        );
+        for openings in &self.boundary_openings {
+            write_openings(&mut out, openings);
+        }
+        write_openings(&mut out, &self.randomizer_openings);
+        out
+    }
+
+    // length of to_bytes
+    pub fn proof_byte_len(&self) -> usize {
+        self.to_bytes().len()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<StarkProof> {
+        let mut pos = 0;
+        let boundary_roots = read_words(bytes, &mut pos)?;
+        let randomizer_root = read_word(bytes, &mut pos)?;
+        let fri_len = usize::try_from(read_u32(bytes, &mut pos)?).unwrap();
+        let fri = FriProof::from_bytes(bytes.get(pos..(pos + fri_len))?)?;
+        pos += fri_len;
+        let mut boundary_openings = Vec::new();
+        for _ in 0..read_u32(bytes, &mut pos)? {
+            boundary_openings.push(read_openings(bytes, &mut pos)?);
+        }
+        let randomizer_openings = read_openings(bytes, &mut pos)?;
+        if pos != bytes.len() {
+            return None;
+        }
+        Some(StarkProof {
+            boundary_roots,
+            randomizer_root,
+            fri,
+            boundary_openings,
+            randomizer_openings,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StarkConfig<T: FieldElement> {
+    // offset of the coset the trace is extended over, None uses
+    // the field generator. it must be nonzero and outside the
+    // fri domain subgroup
+    pub offset: Option<T>,
+    // ratio of the fri domain to the randomized trace domain
+    pub expansion_factor: u32,
+    // fri queries, each adds about log2(expansion_factor)
+    // bits of conjectured security
+    pub colinearity_test_count: u32,
+    // random rows appended to the trace to hide it
+    pub randomizer_count: u32,
+    // highest degree of any transition constraint
+    pub transition_constraints_degree: u32,
+    // conjectured bits of security of the fri queries
+    pub security_level: u32,
+}
+
+impl<T: FieldElement> StarkConfig<T> {
+    // enough queries for n bits at an expansion factor of 32
+    // and degree 2 constraints
+    pub fn for_security_bits(n: u32) -> StarkConfig<T> {
+        let expansion_factor: u32 = 32;
+        let bits_per_test = expansion_factor.ilog2();
+        let colinearity_test_count = n.div_ceil(bits_per_test);
+        StarkConfig {
+            offset: None,
+            expansion_factor,
+            colinearity_test_count,
+            randomizer_count: 4 * colinearity_test_count,
+            transition_constraints_degree: 2,
+            security_level: colinearity_test_count * bits_per_test,
+        }
+    }
+
+    // the fri domain a trace of this many rows is extended
+    // over, None if it doesn't fit a u32
+    pub fn fri_domain_len(&self, original_trace_len: u32) -> Option<u32> {
+        let degree = original_trace_len
+            .checked_add(self.randomizer_count)?
+            .checked_mul(self.transition_constraints_degree)?;
+        let omicron_domain_len = 2_u32.checked_pow(u32::BITS - degree.leading_zeros())?;
+        omicron_domain_len.checked_mul(self.expansion_factor)
+    }
+}
+
+// a trace with its transition and boundary constraints
+pub type Statement<T> = (Vec<Vec<T>>, Vec<MPolynomial<T>>, Vec<(u32, u32, T)>);
+
+// H hashes both the merkle trees and the channel, prover and
+// verifier must use the same one
+pub struct Stark<T: FieldElement, H: TreeHasher + ChannelHasher = Blake3Hasher> {
     offset: T,
     field: Rc<Field<T>>,
     randomizer_count: u32,
@@ -22,6 +392,14 @@ pub struct Stark<T: FieldElement> {
     omicron: T,
     omicron_domain: Vec<T>,
     fri: Fri<T>,
+    // values repeating over the trace and the polynomials
+    // taking them over the omicron domain
+    periodic_columns: Vec<Vec<T>>,
+    periodic_polys: Vec<Polynomial<T>>,
+    // kept so a worker thread can build its own copy
+    #[cfg_attr(not(feature = "rayon"), allow(dead_code))]
+    config: StarkConfig<T>,
+    hasher: PhantomData<H>,
 }
 
 impl<T: FieldElement> Stark<T> {
@@ -34,16 +412,92 @@ impl<T: FieldElement> Stark<T> {
         colinearity_test_count: u32,
         transition_constraints_degree: u32,
     ) -> Stark<T> {
-        let randomizer_count = 4 * colinearity_test_count;
-        let trace_bits = T::from_u32(
-            (original_trace_len + randomizer_count) * transition_constraints_degree,
-            field.p(),
-        )
-        .bits();
-        let omicron_domain_len = 2_u32.pow(u32::try_from(trace_bits).unwrap());
-        let fri_domain_len = omicron_domain_len * expansion_factor;
+        let config = StarkConfig {
+            offset: Some(offset.clone()),
+            expansion_factor,
+            colinearity_test_count,
+            randomizer_count: 4 * colinearity_test_count,
+            transition_constraints_degree,
+            security_level: colinearity_test_count * expansion_factor.ilog2(),
+        };
+        Self::with_config(field, register_count, original_trace_len, &config)
+    }
+
+    pub fn with_config(
+        field: &Rc<Field<T>>,
+        register_count: u32,
+        original_trace_len: u32,
+        config: &StarkConfig<T>,
+    ) -> Stark<T> {
+        Stark::with_hasher(field, register_count, original_trace_len, config)
+    }
+
+    pub fn try_with_config(
+        field: &Rc<Field<T>>,
+        register_count: u32,
+        original_trace_len: u32,
+        config: &StarkConfig<T>,
+    ) -> Result<Stark<T>, StarkError> {
+        Stark::try_with_hasher(field, register_count, original_trace_len, config)
+    }
+}
+
+impl<T: FieldElement, H: TreeHasher + ChannelHasher> Stark<T, H> {
+    pub fn with_hasher(
+        field: &Rc<Field<T>>,
+        register_count: u32,
+        original_trace_len: u32,
+        config: &StarkConfig<T>,
+    ) -> Stark<T, H> {
+        match Self::try_with_hasher(field, register_count, original_trace_len, config) {
+            Ok(stark) => stark,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    // like with_hasher, but a config with_hasher would panic on
+    // is returned as an error
+    pub fn try_with_hasher(
+        field: &Rc<Field<T>>,
+        register_count: u32,
+        original_trace_len: u32,
+        config: &StarkConfig<T>,
+    ) -> Result<Stark<T, H>, StarkError> {
+        let offset = config.offset.as_ref().unwrap_or(field.g());
+        debug_assert!(
+            &offset.get_params() == field.p(),
+            "offset is not an element of the field"
+        );
+        let StarkConfig {
+            offset: _,
+            expansion_factor,
+            colinearity_test_count,
+            randomizer_count,
+            transition_constraints_degree: _,
+            security_level: _,
+        } = *config;
+        let too_long = StarkError::TraceTooLong {
+            len: original_trace_len,
+        };
+        let fri_domain_len = config
+            .fri_domain_len(original_trace_len)
+            .ok_or(too_long.clone())?;
+        Fri::<T>::fold_round_count(fri_domain_len, expansion_factor, colinearity_test_count, 0)
+            .map_err(|reason| StarkError::InvalidConfig { reason })?;
+        // the field needs a subgroup as large as the fri domain
+        if fri_domain_len.ilog2() > field.two_adicity() {
+            return Err(too_long);
+        }
+        let omicron_domain_len = fri_domain_len / expansion_factor;
         let (omega, _) = field.generator_cache(&fri_domain_len);
         let (omicron, _) = field.generator_cache(&omicron_domain_len);
+        // the coset must be disjoint from the fri domain, and so
+        // from the trace domain the zeroifiers vanish on
+        if offset == &field.zero()
+            || field.exp(offset, &T::from_u32(fri_domain_len, field.p())) == field.one()
+        {
+            return Err(StarkError::InvalidOffset);
+        }
 
         let fri = Fri::new(
             &FriOptions {
@@ -52,11 +506,13 @@ impl<T: FieldElement> Stark<T> {
                 domain_len: fri_domain_len,
                 expansion_factor,
                 colinearity_test_count,
+                pow_bits: 0,
+                final_degree: 0,
             },
             field,
         );
 
-        Stark {
+        Ok(Stark {
             offset: offset.clone(),
             field: Rc::clone(field),
             randomizer_count,
@@ -69,18 +525,77 @@ impl<T: FieldElement> Stark<T> {
             omicron_domain: field.domain(&omicron, omicron_domain_len),
             fri,
             fri_domain_len,
+            periodic_columns: Vec::new(),
+            periodic_polys: Vec::new(),
+            config: StarkConfig {
+                offset: Some(offset.clone()),
+                ..config.clone()
+            },
+            hasher: PhantomData,
+        })
+    }
+
+    // columns that transition constraints can reference after the
+    // two states, so variable 1 + 2 * register_count + i is the
+    // value of column i at the current cycle. prover and verifier
+    // must set the same columns
+    pub fn set_periodic_columns(&mut self, columns: &[Vec<T>]) -> Result<(), StarkError> {
+        let omicron_domain_len = self.omicron_domain.len();
+        let mut polys = Vec::new();
+        for (column, values) in columns.iter().enumerate() {
+            let len = values.len();
+            if !len.is_power_of_two() || len > omicron_domain_len {
+                return Err(StarkError::InvalidPeriodicColumn { column, len });
+            }
+            // q interpolates the column over the subgroup of order
+            // len, q(x^step) then repeats it over the omicron domain
+            let step = omicron_domain_len / len;
+            let domain: Vec<T> = (0..len)
+                .map(|i| self.omicron_domain[i * step].clone())
+                .collect();
+            let q = Polynomial::lagrange(&domain, values, &self.field);
+            let mut coefs = vec![self.field.zero(); (len - 1) * step + 1];
+            for (i, coef) in q.coefs().iter().enumerate() {
+                coefs[i * step] = coef.clone();
+            }
+            polys.push(Polynomial::from_coefs(coefs, &self.field));
         }
+        self.periodic_columns = columns.to_vec();
+        self.periodic_polys = polys;
+        Ok(())
     }
 
     pub fn field(&self) -> &Rc<Field<T>> {
         &self.field
     }
 
+    // number of bytes prove will push to the channel, the
+    // binary proof adds a few length prefixes on top
+    pub fn expected_proof_size(&self) -> usize {
+        let register_count = usize::try_from(self.register_count).unwrap();
+        let fri_domain_len = usize::try_from(self.fri_domain_len).unwrap();
+        let opening_count = 4 * usize::try_from(self.fri.colinearity_test_count).unwrap();
+        // a path holds both nodes of each level below the root
+        let path_len = 2 * usize::try_from(fri_domain_len.ilog2()).unwrap();
+
+        // boundary quotient roots and the randomizer root
+        let mut words = register_count + 1;
+        // a value and path for every opening of each boundary
+        // quotient and the randomizer
+        words += (register_count + 1) * opening_count * (1 + path_len);
+        32 * words + self.fri.proof_size_estimate()
+    }
+
     fn transition_degree_bounds(&self, constraint: &MPolynomial<T>) -> u32 {
         let degree: u32 = self.original_trace_len + self.randomizer_count - 1;
         let mut point_degrees: Vec<u32> =
             vec![degree; usize::try_from(1 + 2 * self.register_count).unwrap()];
         point_degrees[0] = 1;
+        point_degrees.extend(
+            self.periodic_polys
+                .iter()
+                .map(|p| u32::try_from(p.degree()).unwrap()),
+        );
         let mut out: u32 = 0;
         for (exps, _) in constraint.exps() {
             let mut sum = 0;
@@ -94,6 +609,18 @@ impl<T: FieldElement> Stark<T> {
         out
     }
 
+    // the degree of each constraint's quotient by the transition
+    // zeroifier once the randomized trace polynomials are
+    // substituted in. this counts every variable at the trace
+    // degree, so it bounds the exact per-term degree from above
+    pub fn constraint_degree_bounds(&self, constraints: &[MPolynomial<T>]) -> Vec<u32> {
+        let degree = self.original_trace_len + self.randomizer_count - 1;
+        constraints
+            .iter()
+            .map(|c| (c.total_degree() * degree).saturating_sub(self.original_trace_len - 1))
+            .collect()
+    }
+
     fn transition_quotient_degree_bound(&self, constraint: &MPolynomial<T>) -> u32 {
         self.transition_degree_bounds(constraint) - (self.original_trace_len - 1)
     }
@@ -174,23 +701,199 @@ impl<T: FieldElement> Stark<T> {
         out
     }
 
-    pub fn prove(
+    fn check_inputs(
         &self,
-        trace: &Vec<Vec<T>>,
-        transition_constraints: &Vec<MPolynomial<T>>,
-        boundary: &Vec<(u32, u32, T)>,
-    ) -> String {
-        let mut trace = trace.clone();
-        let mut channel = Channel::new();
+        trace: &[Vec<T>],
+        transition_constraints: &[MPolynomial<T>],
+        boundary: &[(u32, u32, T)],
+    ) -> Result<(), StarkError> {
+        let register_count = usize::try_from(self.register_count).unwrap();
+        let expected = usize::try_from(self.original_trace_len).unwrap();
+        if trace.len() != expected {
+            return Err(StarkError::TraceLength {
+                len: trace.len(),
+                expected,
+            });
+        }
+        if let Some((row, r)) = trace
+            .iter()
+            .enumerate()
+            .find(|(_, r)| r.len() != register_count)
+        {
+            return Err(StarkError::InconsistentTrace {
+                row,
+                len: r.len(),
+                expected: register_count,
+            });
+        }
+        self.check_statement(transition_constraints, boundary)?;
+        self.check_trace(trace, transition_constraints, boundary)
+            .map_err(|v| match v {
+                ConstraintViolation::Transition { row, constraint } => {
+                    StarkError::UnsatisfiedConstraint { constraint, row }
+                }
+                ConstraintViolation::Boundary { constraint, .. } => {
+                    StarkError::UnsatisfiedBoundary {
+                        cycle: boundary[constraint].0,
+                        register: boundary[constraint].1,
+                    }
+                }
+            })
+    }
 
-        for _ in 0..self.randomizer_count {
-            let mut r: Vec<T> = Vec::new();
-            for _ in 0..self.register_count {
-                r.push(self.field.random());
+    // the checks on constraints that don't need a trace, so
+    // the verifier can reject a malformed statement too
+    fn check_statement(
+        &self,
+        transition_constraints: &[MPolynomial<T>],
+        boundary: &[(u32, u32, T)],
+    ) -> Result<(), StarkError> {
+        if transition_constraints.is_empty() {
+            return Err(StarkError::NoTransitionConstraints);
+        }
+        let register_count = usize::try_from(self.register_count).unwrap();
+        if let Some((cycle, register, _)) = boundary
+            .iter()
+            .find(|(c, r, _)| *c >= self.original_trace_len || *r >= self.register_count)
+        {
+            return Err(StarkError::InvalidBoundary {
+                cycle: *cycle,
+                register: *register,
+            });
+        }
+        // exponent vectors are trimmed, so their length is one
+        // more than the highest variable used
+        let max = 1 + 2 * register_count + self.periodic_columns.len();
+        let count = transition_constraints
+            .iter()
+            .flat_map(|c| c.exps().keys().map(|e| e.len()))
+            .max()
+            .unwrap_or(0);
+        if count > max {
+            return Err(StarkError::ConstraintVariables { count, max });
+        }
+        // fri proves degree below the omicron domain length, so
+        // every quotient has to fit inside it
+        let max = u32::try_from(self.omicron_domain.len()).unwrap() - 1;
+        if let Some((constraint, degree)) = self
+            .constraint_degree_bounds(transition_constraints)
+            .into_iter()
+            .enumerate()
+            .find(|(_, d)| *d > max)
+        {
+            return Err(StarkError::ConstraintDegree {
+                constraint,
+                degree,
+                max,
+            });
+        }
+        Ok(())
+    }
+
+    // evaluate the constraints directly on the trace, without any
+    // commitment or fri work, to find where a trace is wrong.
+    // the trace is assumed to have the shape the stark was built
+    // for, which prove checks before calling this
+    pub fn check_trace(
+        &self,
+        trace: &[Vec<T>],
+        transition_constraints: &[MPolynomial<T>],
+        boundary: &[(u32, u32, T)],
+    ) -> Result<(), ConstraintViolation> {
+        for (row, rows) in trace.windows(2).enumerate() {
+            let mut point = vec![self.omicron_domain[row].clone()];
+            point.extend(rows[0].iter().cloned());
+            point.extend(rows[1].iter().cloned());
+            point.extend(
+                self.periodic_columns
+                    .iter()
+                    .map(|c| c[row % c.len()].clone()),
+            );
+            for (constraint, c) in transition_constraints.iter().enumerate() {
+                if c.eval(&point) != self.field.zero() {
+                    return Err(ConstraintViolation::Transition { row, constraint });
+                }
             }
-            trace.push(r);
         }
+        for (constraint, (cycle, register, value)) in boundary.iter().enumerate() {
+            let row = usize::try_from(*cycle).unwrap();
+            let actual = trace
+                .get(row)
+                .and_then(|r| r.get(usize::try_from(*register).unwrap()));
+            if actual != Some(value) {
+                return Err(ConstraintViolation::Boundary { row, constraint });
+            }
+        }
+        Ok(())
+    }
 
+    // repeated (cycle, register) entries with the same value are
+    // kept once, in the order they first appear
+    fn dedup_boundary(boundary: &[(u32, u32, T)]) -> Result<Vec<(u32, u32, T)>, StarkError> {
+        let mut seen = HashMap::new();
+        let mut out = Vec::new();
+        for (cycle, register, value) in boundary {
+            match seen.get(&(*cycle, *register)) {
+                Some(v) if v == value => {}
+                Some(_) => {
+                    return Err(StarkError::ConflictingBoundary {
+                        cycle: *cycle,
+                        register: *register,
+                    })
+                }
+                None => {
+                    seen.insert((*cycle, *register), value.clone());
+                    out.push((*cycle, *register, value.clone()));
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    // evaluate polynomials over the fri domain coset
+    #[cfg_attr(feature = "rayon", allow(dead_code))]
+    fn extend_serial(&self, polys: &[Polynomial<T>]) -> Vec<Vec<T>> {
+        Polynomial::eval_batch_batch_coset(
+            polys.to_vec(),
+            &self.fri.offset,
+            self.fri_domain_len,
+            &self.field,
+        )
+    }
+
+    #[cfg(feature = "rayon")]
+    fn extend_parallel(&self, polys: &[Polynomial<T>]) -> Vec<Vec<T>> {
+        let field: &Field<T> = &self.field;
+        let offset_domain = field.domain(&self.fri.offset, self.fri_domain_len);
+        let domain = &self.omega_domain;
+        polys
+            .iter()
+            .map(|p| p.coefs())
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|coefs| {
+                let scaled = coefs
+                    .iter()
+                    .zip(&offset_domain)
+                    .map(|(c, o)| field.mul(c, o))
+                    .collect();
+                Polynomial::eval_fft(&scaled, domain, field)
+            })
+            .collect()
+    }
+
+    // the codewords are independent, so with the rayon feature
+    // each one is evaluated on its own thread
+    fn extend(&self, polys: &[Polynomial<T>]) -> Vec<Vec<T>> {
+        #[cfg(feature = "rayon")]
+        return self.extend_parallel(polys);
+        #[cfg(not(feature = "rayon"))]
+        self.extend_serial(polys)
+    }
+
+    // one polynomial per register, through the register's value
+    // at each row over the start of the omicron domain
+    fn trace_polys(&self, trace: &[Vec<T>]) -> Vec<Polynomial<T>> {
         let mut trace_domain = vec![self.field.zero(); trace.len()];
         trace_domain.clone_from_slice(&self.omicron_domain[0..trace.len()]);
 
@@ -202,8 +905,118 @@ impl<T: FieldElement> Stark<T> {
                 .collect();
             y_vals.push(trace_vals);
         }
-        let trace_polys =
-            Polynomial::interpolate_fft_batch(&trace_domain, &y_vals[0..], &self.field);
+        Polynomial::interpolate_fft_batch(&trace_domain, &y_vals[0..], &self.field)
+    }
+
+    // the coset of the fri domain every codeword is evaluated over
+    pub fn evaluation_domain(&self) -> Vec<T> {
+        self.omega_domain
+            .iter()
+            .map(|v| self.field.mul(&self.offset, v))
+            .collect()
+    }
+
+    // the low degree extension of each register over the
+    // evaluation domain. prove appends random rows before
+    // extending, so this is the extension of exactly the rows given
+    pub fn trace_codewords(&self, trace: &[Vec<T>]) -> Vec<Vec<T>> {
+        self.extend(&self.trace_polys(trace))
+    }
+
+    // bind every challenge to the statement being proven,
+    // public inputs are whatever else prover and verifier
+    // agree on
+    fn absorb_public_inputs(
+        &self,
+        channel: &mut Channel<H>,
+        transition_constraints: &[MPolynomial<T>],
+        boundary: &[(u32, u32, T)],
+        public_inputs: &[T],
+    ) {
+        for v in [
+            self.original_trace_len,
+            self.register_count,
+            self.randomizer_count,
+            self.expansion_factor,
+            self.fri_domain_len,
+        ] {
+            channel.absorb_public(&v.to_le_bytes());
+        }
+        for (cycle, register, value) in boundary {
+            channel.absorb_public(&cycle.to_le_bytes());
+            channel.absorb_public(&register.to_le_bytes());
+            channel.absorb_public(&value.to_bytes_le_sized());
+        }
+        channel.absorb_public(
+            &u32::try_from(transition_constraints.len())
+                .unwrap()
+                .to_le_bytes(),
+        );
+        for constraint in transition_constraints {
+            // sum the terms by trimmed exponents and drop zeros, so
+            // equal polynomials absorb the same bytes in the same
+            // order whatever their maps hold
+            let zero = self.field.zero();
+            let mut terms: BTreeMap<&[u32], T> = BTreeMap::new();
+            for (exps, coef) in constraint.exps() {
+                let len = exps.iter().rposition(|e| *e != 0).map_or(0, |i| i + 1);
+                let sum = terms.entry(&exps[..len]).or_insert_with(|| zero.clone());
+                *sum = self.field.add(sum, coef);
+            }
+            terms.retain(|_, coef| *coef != zero);
+            channel.absorb_public(&u32::try_from(terms.len()).unwrap().to_le_bytes());
+            for (exps, coef) in terms {
+                channel.absorb_public(&u32::try_from(exps.len()).unwrap().to_le_bytes());
+                for e in exps {
+                    channel.absorb_public(&e.to_le_bytes());
+                }
+                channel.absorb_public(&coef.to_bytes_le_sized());
+            }
+        }
+        channel.absorb_public(&u32::try_from(public_inputs.len()).unwrap().to_le_bytes());
+        for v in public_inputs {
+            channel.absorb_public(&v.to_bytes_le_sized());
+        }
+        channel.absorb_public(
+            &u32::try_from(self.periodic_columns.len())
+                .unwrap()
+                .to_le_bytes(),
+        );
+        for column in &self.periodic_columns {
+            channel.absorb_public(&u32::try_from(column.len()).unwrap().to_le_bytes());
+            for v in column {
+                channel.absorb_public(&v.to_bytes_le_sized());
+            }
+        }
+    }
+
+    pub fn prove(
+        &self,
+        trace: &Vec<Vec<T>>,
+        transition_constraints: &Vec<MPolynomial<T>>,
+        boundary: &[(u32, u32, T)],
+        public_inputs: &[T],
+    ) -> Result<String, StarkError> {
+        let boundary = &Self::dedup_boundary(boundary)?;
+        self.check_inputs(trace, transition_constraints, boundary)?;
+        let mut trace = trace.clone();
+        let mut channel = Channel::<H>::prover_with_hasher();
+        self.absorb_public_inputs(
+            &mut channel,
+            transition_constraints,
+            boundary,
+            public_inputs,
+        );
+
+        for _ in 0..self.randomizer_count {
+            let mut r: Vec<T> = Vec::new();
+            for _ in 0..self.register_count {
+                r.push(self.field.random());
+            }
+            trace.push(r);
+        }
+
+        let trace_polys = self.trace_polys(&trace);
 
         let boundary_interpolants = self.boundary_interpolants(boundary);
         let boundary_zeroifiers = self.boundary_zeroifiers(boundary);
@@ -225,17 +1038,12 @@ impl<T: FieldElement> Stark<T> {
         }
 
         let mut boundary_quotient_codewords = Vec::new();
-        let mut boundary_quotient_trees: Vec<Tree<T>> = Vec::new();
-        let codewords = Polynomial::eval_batch_batch_coset(
-            boundary_quotients.clone(),
-            &self.fri.offset,
-            self.fri_domain_len,
-            &self.field,
-        );
+        let mut boundary_quotient_trees: Vec<Tree<T, H>> = Vec::new();
+        let codewords = self.extend(&boundary_quotients);
         for i in 0..usize::try_from(self.register_count).unwrap() {
             let c = codewords[i].iter().map(|v| v.to_bytes_le_sized()).collect();
             let tree = Tree::build(&c);
-            channel.push_single(&tree.root());
+            channel.push_single_labeled(BOUNDARY_ROOT_LABEL, &tree.root());
             boundary_quotient_codewords.push(c);
             boundary_quotient_trees.push(tree);
         }
@@ -252,6 +1060,7 @@ impl<T: FieldElement> Stark<T> {
             pp.scale_precalc(&self.omicron, &self.omicron_domain);
             pp
         }));
+        point.extend(self.periodic_polys.clone());
 
         let mut transition_weights = Vec::new();
         {
@@ -265,10 +1074,11 @@ impl<T: FieldElement> Stark<T> {
         }
 
         // combine all transition constraints using a random linear combination
-        let mut single_transition_constraint = MPolynomial::new(&self.field);
-        for (i, t) in transition_constraints.iter().enumerate() {
-            single_transition_constraint.add(t.clone().mul_scalar(&transition_weights[i]));
-        }
+        let single_transition_constraint = MPolynomial::random_linear_combination(
+            transition_constraints,
+            &transition_weights,
+            &self.field,
+        );
 
         let transition_polynomial = single_transition_constraint.eval_symbolic(&point);
         let transition_zeroifier = self.transition_zeroifier();
@@ -281,20 +1091,22 @@ impl<T: FieldElement> Stark<T> {
             &self.field,
         );
 
-        let mut randomizer_poly = Polynomial::new(&self.field);
         let transition_max_degree = self.max_degree(&single_transition_constraint);
-        for i in 0..(transition_max_degree + 1) {
-            randomizer_poly.term(&self.field.random(), i);
-        }
+        let randomizer_poly = Polynomial::from_coefs(
+            (0..(transition_max_degree + 1))
+                .map(|_| self.field.random())
+                .collect(),
+            &self.field,
+        );
 
         let randomizer_codeword = randomizer_poly
             .eval_batch_coset(&self.fri.offset, self.fri_domain_len)
             .iter()
             .map(|v| v.to_bytes_le_sized())
             .collect::<Vec<[u8; 32]>>();
-        let randomizer_tree: Tree<T> = Tree::build(&randomizer_codeword);
+        let randomizer_tree: Tree<T, H> = Tree::build(&randomizer_codeword);
         let randomizer_root = randomizer_tree.root();
-        channel.push_single(&randomizer_root);
+        channel.push_single_labeled(RANDOMIZER_ROOT_LABEL, &randomizer_root);
 
         let count =
             u32::try_from(1 + 2/*transition_quotients.len()*/ + 2 * boundary_quotients.len())
@@ -329,10 +1141,7 @@ impl<T: FieldElement> Stark<T> {
 
         let mut combination = Polynomial::new(&self.field);
         for i in 0..weights.len() {
-            let mut w_poly = Polynomial::new(&self.field);
-            w_poly.term(&weights[i], 0);
-            w_poly.mul(&terms[i]);
-            combination.add(&w_poly);
+            combination.add(&terms[i].times_scalar(&weights[i]));
         }
 
         let combined_codeword = combination
@@ -380,16 +1189,220 @@ impl<T: FieldElement> Stark<T> {
             channel.push(&path);
         }
 
-        channel.serialize()
+        Ok(channel.serialize())
     }
 
     pub fn verify(
         &self,
         proof: &str,
         transition_constraints: &Vec<MPolynomial<T>>,
-        boundary: &Vec<(u32, u32, T)>,
-    ) -> bool {
-        let mut channel = Channel::deserialize(proof);
+        boundary: &[(u32, u32, T)],
+        public_inputs: &[T],
+    ) -> Result<(), StarkError> {
+        // reading the proof out first means a truncated or
+        // misshapen one is rejected rather than panicking
+        let mut channel = Channel::<H>::try_deserialize(proof).ok_or(StarkError::MalformedProof)?;
+        let proof = self
+            .pull_proof(&mut channel)
+            .ok_or(StarkError::MalformedProof)?;
+        self.verify_proof(&proof, transition_constraints, boundary, public_inputs)
+    }
+
+    // prove several statements of the same shape with one setup,
+    // returning the error of the first that fails. with the rayon
+    // feature the statements are proven on separate threads
+    pub fn prove_batch(&self, statements: &[Statement<T>]) -> Result<Vec<String>, StarkError> {
+        #[cfg(feature = "rayon")]
+        return self.prove_batch_parallel(statements);
+        #[cfg(not(feature = "rayon"))]
+        statements
+            .iter()
+            .map(|(trace, transition_constraints, boundary)| {
+                self.prove(trace, transition_constraints, boundary, &[])
+            })
+            .collect()
+    }
+
+    // the field and polynomials are held in an Rc, so each worker
+    // builds its own stark and rebuilds the constraints from their
+    // terms. every statement is proven before the first error is
+    // picked, so the result doesn't depend on thread timing
+    #[cfg(feature = "rayon")]
+    fn prove_batch_parallel(&self, statements: &[Statement<T>]) -> Result<Vec<String>, StarkError> {
+        let g = self.field.g();
+        let register_count = self.register_count;
+        let original_trace_len = self.original_trace_len;
+        let config = &self.config;
+        let periodic_columns = &self.periodic_columns;
+        statements
+            .iter()
+            .map(|(trace, transition_constraints, boundary)| {
+                let exps: Vec<_> = transition_constraints
+                    .iter()
+                    .map(|c| c.exps().clone())
+                    .collect();
+                (trace, exps, boundary)
+            })
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map_init(
+                || {
+                    let field = Rc::new(Field::new(g.clone()));
+                    let mut stark = Stark::<T, H>::with_hasher(
+                        &field,
+                        register_count,
+                        original_trace_len,
+                        config,
+                    );
+                    stark.set_periodic_columns(periodic_columns).unwrap();
+                    stark
+                },
+                |stark, (trace, exps, boundary)| {
+                    let transition_constraints = exps
+                        .iter()
+                        .map(|e| MPolynomial::from_map(e, &stark.field))
+                        .collect();
+                    stark.prove(trace, &transition_constraints, boundary, &[])
+                },
+            )
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    // prove a trace given as one vector per register
+    pub fn prove_columns(
+        &self,
+        columns: &[Vec<T>],
+        transition_constraints: &Vec<MPolynomial<T>>,
+        boundary: &[(u32, u32, T)],
+        public_inputs: &[T],
+    ) -> Result<String, StarkError> {
+        let trace = Self::transpose(columns)?;
+        self.prove(&trace, transition_constraints, boundary, public_inputs)
+    }
+
+    fn transpose(columns: &[Vec<T>]) -> Result<Vec<Vec<T>>, StarkError> {
+        let expected = columns.first().map_or(0, |c| c.len());
+        if let Some((column, c)) = columns
+            .iter()
+            .enumerate()
+            .find(|(_, c)| c.len() != expected)
+        {
+            return Err(StarkError::InconsistentColumns {
+                column,
+                len: c.len(),
+                expected,
+            });
+        }
+        Ok((0..expected)
+            .map(|row| columns.iter().map(|c| c[row].clone()).collect())
+            .collect())
+    }
+
+    pub fn prove_to_proof(
+        &self,
+        trace: &Vec<Vec<T>>,
+        transition_constraints: &Vec<MPolynomial<T>>,
+        boundary: &[(u32, u32, T)],
+        public_inputs: &[T],
+    ) -> Result<StarkProof, StarkError> {
+        let proof = self.prove(trace, transition_constraints, boundary, public_inputs)?;
+        let mut channel = Channel::<H>::deserialize(&proof);
+        Ok(self.pull_proof(&mut channel).unwrap())
+    }
+
+    // read the messages prove pushed back into a proof, None
+    // if a message is missing or the wrong size
+    fn pull_proof(&self, channel: &mut Channel<H>) -> Option<StarkProof> {
+        let boundary_roots = (0..self.register_count)
+            .map(|_| channel.try_pull_root())
+            .collect::<Option<_>>()?;
+        let randomizer_root = channel.try_pull_root()?;
+        let fri = self.fri.pull_proof(channel)?;
+        // each of the test indices is opened at four points
+        let opening_count = 4 * self.fri.colinearity_test_count;
+        let mut pull_openings = || {
+            (0..opening_count)
+                .map(|_| {
+                    Some(StarkOpening {
+                        value: channel.try_pull_root()?,
+                        path: channel.try_pull_path()?,
+                    })
+                })
+                .collect::<Option<Vec<_>>>()
+        };
+        let boundary_openings = (0..self.register_count)
+            .map(|_| pull_openings())
+            .collect::<Option<_>>()?;
+        let randomizer_openings = pull_openings()?;
+        Some(StarkProof {
+            boundary_roots,
+            randomizer_root,
+            fri,
+            boundary_openings,
+            randomizer_openings,
+        })
+    }
+
+    // replay a proof through a channel in the order prove
+    // pushed it
+    pub fn verify_proof(
+        &self,
+        proof: &StarkProof,
+        transition_constraints: &Vec<MPolynomial<T>>,
+        boundary: &[(u32, u32, T)],
+        public_inputs: &[T],
+    ) -> Result<(), StarkError> {
+        let register_count = usize::try_from(self.register_count).unwrap();
+        // each of the test indices is opened at four points
+        let opening_count = usize::try_from(4 * self.fri.colinearity_test_count).unwrap();
+        if proof.boundary_roots.len() != register_count
+            || proof.boundary_openings.len() != register_count
+            || proof
+                .boundary_openings
+                .iter()
+                .chain(std::iter::once(&proof.randomizer_openings))
+                .any(|openings| openings.len() != opening_count)
+        {
+            return Err(StarkError::MalformedProof);
+        }
+        let mut channel = Channel::<H>::with_hasher();
+        for root in &proof.boundary_roots {
+            channel.push_single_labeled(BOUNDARY_ROOT_LABEL, root);
+        }
+        channel.push_single_labeled(RANDOMIZER_ROOT_LABEL, &proof.randomizer_root);
+        self.fri
+            .push_proof(&proof.fri, &mut channel)
+            .map_err(StarkError::Fri)?;
+        for openings in proof
+            .boundary_openings
+            .iter()
+            .chain(std::iter::once(&proof.randomizer_openings))
+        {
+            for opening in openings {
+                channel.push_single(&opening.value);
+                channel.push(&opening.path);
+            }
+        }
+        self.verify_channel(
+            &mut channel,
+            transition_constraints,
+            boundary,
+            public_inputs,
+        )
+    }
+
+    fn verify_channel(
+        &self,
+        channel: &mut Channel<H>,
+        transition_constraints: &Vec<MPolynomial<T>>,
+        boundary: &[(u32, u32, T)],
+        public_inputs: &[T],
+    ) -> Result<(), StarkError> {
+        let boundary = &Self::dedup_boundary(boundary)?;
+        self.check_statement(transition_constraints, boundary)?;
+        self.absorb_public_inputs(channel, transition_constraints, boundary, public_inputs);
         let mut original_trace_len = 0;
         for (c, _, _) in boundary {
             if c > &original_trace_len {
@@ -402,7 +1415,11 @@ impl<T: FieldElement> Stark<T> {
 
         let mut boundary_quotient_roots = Vec::new();
         for _ in 0..self.register_count {
-            boundary_quotient_roots.push(channel.pull_root());
+            boundary_quotient_roots.push(
+                channel
+                    .pull_labeled_root(BOUNDARY_ROOT_LABEL)
+                    .ok_or(StarkError::MalformedProof)?,
+            );
         }
 
         let mut transition_weights = Vec::new();
@@ -417,12 +1434,15 @@ impl<T: FieldElement> Stark<T> {
         }
 
         // combine all transition constraints using a random linear combination
-        let mut single_transition_constraint = MPolynomial::new(&self.field);
-        for (i, t) in transition_constraints.iter().enumerate() {
-            single_transition_constraint.add(t.clone().mul_scalar(&transition_weights[i]));
-        }
+        let single_transition_constraint = MPolynomial::random_linear_combination(
+            transition_constraints,
+            &transition_weights,
+            &self.field,
+        );
 
-        let randomizer_root = channel.pull_root();
+        let randomizer_root = channel
+            .pull_labeled_root(RANDOMIZER_ROOT_LABEL)
+            .ok_or(StarkError::MalformedProof)?;
 
         let count = u32::try_from(
             1 + 2 * transition_constraints.len()
@@ -434,7 +1454,7 @@ impl<T: FieldElement> Stark<T> {
             &T::from_bytes_le(&channel.verifier_hash(), self.field().p()),
         );
 
-        let mut polynomial_vals = self.fri.verify(&mut channel);
+        let mut polynomial_vals = self.fri.verify(channel).map_err(StarkError::Fri)?;
         polynomial_vals.sort_by(|(ax, _ay), (bx, _by)| {
             if ax > bx {
                 return Ordering::Greater;
@@ -465,21 +1485,28 @@ impl<T: FieldElement> Stark<T> {
             for j in duplicated_indices.clone() {
                 leaf_map.insert(j, channel.pull_path()[0]);
                 let path = &channel.pull_path();
-                Tree::<T>::verify(
+                Tree::<T, H>::verify(
                     &boundary_quotient_roots[usize::try_from(i).unwrap()],
                     j,
                     path,
                     leaf_map.get(&j).unwrap(),
-                );
+                )
+                .map_err(|error| StarkError::InvalidBoundaryPath { register: i, error })?;
             }
             leaves.push(leaf_map);
         }
 
         let mut randomizer_map = HashMap::new();
         for i in duplicated_indices {
-            let val = channel.pull().data.clone().try_into().unwrap();
+            let val = channel
+                .pull()
+                .data
+                .clone()
+                .try_into()
+                .map_err(|_| StarkError::MalformedProof)?;
             let path = &channel.pull_path();
-            Tree::<T>::verify(&randomizer_root, i, path, &val);
+            Tree::<T, H>::verify(&randomizer_root, i, path, &val)
+                .map_err(|error| StarkError::InvalidRandomizerPath { error })?;
             randomizer_map.insert(i, val);
         }
 
@@ -495,12 +1522,12 @@ impl<T: FieldElement> Stark<T> {
         for i in 0..indices.len() {
             let current_index = indices[i];
             let domain_current_index = self.field.mul(
-                self.field.g(),
+                &self.offset,
                 &self.omega_domain[usize::try_from(current_index).unwrap()],
             );
             let next_index = (current_index + self.expansion_factor) % self.fri_domain_len;
             let domain_next_index = self.field.mul(
-                self.field.g(),
+                &self.offset,
                 &self.omega_domain[usize::try_from(next_index).unwrap()],
             );
             let mut current_trace =
@@ -532,6 +1559,11 @@ impl<T: FieldElement> Stark<T> {
             point.push(domain_current_index.clone());
             point.extend(current_trace.clone());
             point.extend(next_trace.clone());
+            point.extend(
+                self.periodic_polys
+                    .iter()
+                    .map(|p| p.eval(&domain_current_index)),
+            );
 
             let transition_constraint_value = single_transition_constraint.eval(&point);
             let transition_zeroifier_eval_inv = self
@@ -576,18 +1608,20 @@ impl<T: FieldElement> Stark<T> {
                 }
             }
 
-            let mut combination = self.field.zero();
-            for j in 0..terms.len() {
-                combination = self
-                    .field
-                    .add(&combination, &self.field.mul(&terms[j], &weights[j]));
-            }
+            let weighted_terms: Vec<T> = terms
+                .iter()
+                .zip(weights.iter())
+                .map(|(term, weight)| self.field.mul(term, weight))
+                .collect();
+            let combination = self.field.sum(&weighted_terms);
             if combination != values[i] {
-                panic!("invalid combination value");
+                return Err(StarkError::CombinationMismatch {
+                    index: current_index,
+                });
             }
         }
 
-        true
+        Ok(())
     }
 }
 
@@ -595,12 +1629,22 @@ impl<T: FieldElement> Stark<T> {
 mod tests {
     use std::time::Instant;
     use crate::field_element::{ParamWrapper, CryptoBigIntElement, UC};
+    use crate::tree::KeccakHasher;
     use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
 
     use super::*;
 
-    #[test]
-    fn should_make_verify_stark_proof() {
+    // stark, trace, transition constraints, boundary constraints
+    type TestStark = (
+        Stark<CryptoBigIntElement>,
+        Vec<Vec<CryptoBigIntElement>>,
+        Vec<MPolynomial<CryptoBigIntElement>>,
+        Vec<(u32, u32, CryptoBigIntElement)>,
+    );
+
+    // a two register trace where each value is the square of
+    // the previous one
+    fn squares_stark() -> TestStark {
         let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(1_u128 + 407_u128 * 2_u128.pow(119))));
         let g = CryptoBigIntElement(DynResidue::new(&UC::from_u128(85408008396924667383611388730472331217_u128), p.0));
         let f = Rc::new(Field::new(g.clone()));
@@ -642,10 +1686,847 @@ mod tests {
             c.sub(&next_state[1]);
             transition_constraints.push(c);
         }
+        (stark, trace, transition_constraints, boundary_constraints)
+    }
+
+    #[test]
+    fn should_make_verify_stark_proof() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
         let ins = Instant::now();
-        let proof = stark.prove(&trace, &transition_constraints, &boundary_constraints);
+        let proof = stark
+            .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
         println!("prove: {:?}", ins.elapsed());
-        stark.verify(&proof, &transition_constraints, &boundary_constraints);
+        assert_eq!(
+            stark.verify(&proof, &transition_constraints, &boundary_constraints, &[]),
+            Ok(())
+        );
         println!("verify: {:?}", ins.elapsed());
     }
+
+    #[test]
+    fn should_bind_public_inputs() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let f = stark.field().clone();
+        let public_inputs = vec![f.bigint(5), f.bigint(6)];
+        let proof = stark
+            .prove(
+                &trace,
+                &transition_constraints,
+                &boundary_constraints,
+                &public_inputs,
+            )
+            .unwrap();
+        assert_eq!(
+            stark.verify(
+                &proof,
+                &transition_constraints,
+                &boundary_constraints,
+                &public_inputs
+            ),
+            Ok(())
+        );
+
+        // changing, dropping or adding an input changes every challenge
+        for other in [
+            vec![f.bigint(5), f.bigint(7)],
+            vec![f.bigint(5)],
+            vec![f.bigint(5), f.bigint(6), f.zero()],
+        ] {
+            assert!(stark
+                .verify(
+                    &proof,
+                    &transition_constraints,
+                    &boundary_constraints,
+                    &other
+                )
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn should_bind_transition_constraints() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let f = stark.field().clone();
+        let proof = stark
+            .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+
+        // shift a multiple of x1 between the constraints so the
+        // random combination is unchanged for the challenge the
+        // prover saw. this only fails if the constraints are
+        // absorbed before the challenge is drawn
+        let mut channel: Channel = Channel::new_verifier(&proof);
+        stark.absorb_public_inputs(
+            &mut channel,
+            &transition_constraints,
+            &boundary_constraints,
+            &[],
+        );
+        for _ in 0..2 {
+            channel.pull_labeled_root(BOUNDARY_ROOT_LABEL).unwrap();
+        }
+        let coef = f.sample(CryptoBigIntElement::from_bytes_le(
+            &channel.verifier_hash(),
+            f.p(),
+        ));
+        let x = MPolynomial::variables(5, &f);
+        let forged = vec![
+            transition_constraints[0].minus(&x[1].times_scalar(&coef)),
+            transition_constraints[1].plus(&x[1]),
+        ];
+        assert!(stark
+            .verify(&proof, &forged, &boundary_constraints, &[])
+            .is_err());
+
+        // the same polynomials with an untrimmed exponent vector
+        // and a zero term still verify
+        let mut exps = transition_constraints[0].exps().clone();
+        let (k, v) = exps
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .next()
+            .unwrap();
+        exps.remove(&k);
+        let mut untrimmed = k.clone();
+        untrimmed.push(0);
+        exps.insert(untrimmed, v);
+        exps.insert(vec![0, 0, 0, 0, 1], f.zero());
+        let equivalent = vec![
+            MPolynomial::from_map(&exps, &f),
+            transition_constraints[1].clone(),
+        ];
+        assert_eq!(
+            stark.verify(&proof, &equivalent, &boundary_constraints, &[]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_prove_verify_with_keccak() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let config = StarkConfig {
+            offset: Some(stark.offset.clone()),
+            expansion_factor: 32,
+            colinearity_test_count: 26,
+            randomizer_count: 104,
+            transition_constraints_degree: 2,
+            security_level: 130,
+        };
+        let keccak_stark =
+            Stark::<CryptoBigIntElement, KeccakHasher>::with_hasher(stark.field(), 2, 40, &config);
+        let proof = keccak_stark
+            .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        assert_eq!(
+            keccak_stark.verify(&proof, &transition_constraints, &boundary_constraints, &[]),
+            Ok(())
+        );
+        let binary = keccak_stark
+            .prove_to_proof(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        assert_eq!(
+            keccak_stark.verify_proof(&binary, &transition_constraints, &boundary_constraints, &[]),
+            Ok(())
+        );
+
+        // the roots and challenges differ under blake3
+        assert!(stark
+            .verify(&proof, &transition_constraints, &boundary_constraints, &[])
+            .is_err());
+    }
+
+    #[test]
+    fn should_prove_batch() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let f = stark.field().clone();
+
+        // the same constraints starting from 5 and 7
+        let mut other_trace = vec![vec![f.bigint(5), f.bigint(7)]];
+        while other_trace.len() < trace.len() {
+            let prev = &other_trace[other_trace.len() - 1];
+            other_trace.push(vec![f.mul(&prev[0], &prev[0]), f.mul(&prev[1], &prev[1])]);
+        }
+        let last = &other_trace[other_trace.len() - 1];
+        let other_boundary = vec![
+            (0, 0, f.bigint(5)),
+            (0, 1, f.bigint(7)),
+            (39, 0, last[0].clone()),
+            (39, 1, last[1].clone()),
+        ];
+
+        let statements = vec![
+            (trace, transition_constraints.clone(), boundary_constraints),
+            (other_trace, transition_constraints, other_boundary),
+        ];
+        let proofs = stark.prove_batch(&statements).unwrap();
+        assert_eq!(proofs.len(), 2);
+        for (proof, (_, transition_constraints, boundary)) in proofs.iter().zip(&statements) {
+            assert_eq!(
+                stark.verify(proof, transition_constraints, boundary, &[]),
+                Ok(())
+            );
+        }
+        assert!(stark
+            .verify(&proofs[0], &statements[1].1, &statements[1].2, &[])
+            .is_err());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn should_return_first_batch_error_in_parallel() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let mut short_trace = trace.clone();
+        short_trace.pop();
+        let mut wrong_boundary = boundary_constraints.clone();
+        wrong_boundary[0].2 = stark.field().bigint(5);
+        let statements = vec![
+            (
+                trace.clone(),
+                transition_constraints.clone(),
+                boundary_constraints.clone(),
+            ),
+            (trace, transition_constraints.clone(), wrong_boundary),
+            (short_trace, transition_constraints, boundary_constraints),
+        ];
+        assert_eq!(
+            stark.prove_batch(&statements),
+            Err(StarkError::UnsatisfiedBoundary {
+                cycle: 0,
+                register: 0
+            })
+        );
+    }
+
+    #[test]
+    fn should_prove_verify_with_custom_offset() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let f = stark.field().clone();
+        let config = StarkConfig {
+            offset: Some(f.exp(&stark.offset, &f.bigint(3))),
+            ..StarkConfig::for_security_bits(128)
+        };
+        let stark = Stark::with_config(&f, 2, 40, &config);
+        let proof = stark
+            .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        assert_eq!(
+            stark.verify(&proof, &transition_constraints, &boundary_constraints, &[]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "offset must be nonzero and outside the evaluation subgroup")]
+    fn should_reject_offset_in_subgroup() {
+        let (stark, _, _, _) = squares_stark();
+        Stark::new(&stark.omega, stark.field(), 2, 40, 32, 26, 2);
+    }
+
+    #[test]
+    fn should_return_invalid_offset() {
+        let (stark, _, _, _) = squares_stark();
+        let f = stark.field().clone();
+        for offset in [
+            f.zero(),
+            f.one(),
+            stark.omega.clone(),
+            stark.omicron.clone(),
+        ] {
+            let config = StarkConfig {
+                offset: Some(offset),
+                ..StarkConfig::for_security_bits(128)
+            };
+            assert_eq!(
+                Stark::try_with_config(&f, 2, 40, &config).err(),
+                Some(StarkError::InvalidOffset)
+            );
+        }
+        let config = StarkConfig {
+            offset: Some(f.exp(&stark.offset, &f.bigint(3))),
+            ..StarkConfig::for_security_bits(128)
+        };
+        assert!(Stark::try_with_config(&f, 2, 40, &config).is_ok());
+    }
+
+    #[test]
+    fn should_return_config_errors() {
+        let (stark, _, _, _) = squares_stark();
+        let f = stark.field().clone();
+        let config = StarkConfig::for_security_bits(128);
+        assert_eq!(
+            Stark::try_with_config(&f, 2, u32::MAX, &config).err(),
+            Some(StarkError::TraceTooLong { len: u32::MAX })
+        );
+        // 3 * 2^30 + 1 has no subgroup of the 2^31 point fri
+        // domain this trace needs
+        let p = ParamWrapper(DynResidueParams::new(&UC::from_u128(3221225473_u128)));
+        let small = Rc::new(Field::new(CryptoBigIntElement::from_u32(5, &p)));
+        assert_eq!(
+            Stark::try_with_config(&small, 2, 1 << 24, &config).err(),
+            Some(StarkError::TraceTooLong { len: 1 << 24 })
+        );
+
+        let config = StarkConfig {
+            expansion_factor: 3,
+            ..StarkConfig::for_security_bits(128)
+        };
+        assert_eq!(
+            Stark::try_with_config(&f, 2, 40, &config).err(),
+            Some(StarkError::InvalidConfig {
+                reason: "fri domain_len must be a power of two"
+            })
+        );
+        // 100 queries need a codeword longer than 400
+        let config = StarkConfig {
+            colinearity_test_count: 100,
+            randomizer_count: 0,
+            ..StarkConfig::for_security_bits(128)
+        };
+        assert!(matches!(
+            Stark::try_with_config(&f, 2, 4, &config),
+            Err(StarkError::InvalidConfig { .. })
+        ));
+        let config = StarkConfig {
+            transition_constraints_degree: 0,
+            ..StarkConfig::for_security_bits(128)
+        };
+        assert_eq!(
+            Stark::try_with_config(&f, 2, 40, &config).err(),
+            Some(StarkError::InvalidConfig {
+                reason: "fri domain_len must be larger than expansion_factor"
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "is too long for the fri domain")]
+    fn should_panic_on_config_errors() {
+        let (stark, _, _, _) = squares_stark();
+        Stark::with_config(
+            stark.field(),
+            2,
+            u32::MAX,
+            &StarkConfig::for_security_bits(128),
+        );
+    }
+
+    #[test]
+    fn should_reject_tampered_stark_proof() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let proof = stark
+            .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        let flip = |index: usize| {
+            let mut channel = Channel::new_verifier(&proof);
+            channel.messages[index].data[0] ^= 1;
+            channel.serialize()
+        };
+
+        // a boundary quotient root, every later challenge changes
+        let tampered = flip(0);
+        assert!(stark
+            .verify(
+                &tampered,
+                &transition_constraints,
+                &boundary_constraints,
+                &[]
+            )
+            .is_err());
+
+        // the last randomizer path
+        let last = Channel::new_verifier(&proof).len() - 1;
+        let tampered = flip(last);
+        assert!(matches!(
+            stark.verify(
+                &tampered,
+                &transition_constraints,
+                &boundary_constraints,
+                &[]
+            ),
+            Err(StarkError::InvalidRandomizerPath { .. })
+        ));
+
+        assert_eq!(
+            stark.verify(
+                "not a proof",
+                &transition_constraints,
+                &boundary_constraints,
+                &[]
+            ),
+            Err(StarkError::MalformedProof)
+        );
+    }
+
+    #[test]
+    fn should_round_trip_binary_stark_proof() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let proof = stark
+            .prove_to_proof(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        let bytes = proof.to_bytes();
+        assert_eq!(StarkProof::from_bytes(&bytes), Some(proof.clone()));
+        assert_eq!(
+            stark.verify_proof(&proof, &transition_constraints, &boundary_constraints, &[]),
+            Ok(())
+        );
+
+        let string_proof = stark
+            .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        assert!(bytes.len() * 3 < string_proof.len());
+
+        let mut tampered = proof.clone();
+        tampered.randomizer_openings[0].value[0] ^= 1;
+        assert!(matches!(
+            stark.verify_proof(
+                &tampered,
+                &transition_constraints,
+                &boundary_constraints,
+                &[]
+            ),
+            Err(StarkError::InvalidRandomizerPath { .. })
+        ));
+
+        let mut truncated = proof.clone();
+        truncated.boundary_roots.pop();
+        assert_eq!(
+            stark.verify_proof(
+                &truncated,
+                &transition_constraints,
+                &boundary_constraints,
+                &[]
+            ),
+            Err(StarkError::MalformedProof)
+        );
+        assert_eq!(StarkProof::from_bytes(&bytes[..bytes.len() - 1]), None);
+    }
+
+    #[test]
+    fn should_label_commitments() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let proof = stark
+            .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        let channel: Channel = Channel::new_verifier(&proof);
+        let labels: Vec<Option<&str>> = channel.messages[0..4]
+            .iter()
+            .map(|m| m.label.as_deref())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                Some(BOUNDARY_ROOT_LABEL),
+                Some(BOUNDARY_ROOT_LABEL),
+                Some(RANDOMIZER_ROOT_LABEL),
+                Some("fri_root")
+            ]
+        );
+
+        // a randomizer root presented as a boundary root
+        let mut relabeled: Channel = Channel::new_verifier(&proof);
+        relabeled.messages[2].label = Some(BOUNDARY_ROOT_LABEL.to_string());
+        assert_eq!(
+            stark.verify_channel(
+                &mut relabeled,
+                &transition_constraints,
+                &boundary_constraints,
+                &[]
+            ),
+            Err(StarkError::MalformedProof)
+        );
+    }
+
+    #[test]
+    fn should_reject_truncated_openings() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let proof = stark
+            .prove_to_proof(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+
+        let mut truncated = proof.clone();
+        truncated.randomizer_openings.truncate(1);
+        // still decodes, the counts are part of the encoding
+        let truncated = StarkProof::from_bytes(&truncated.to_bytes()).unwrap();
+        assert_eq!(
+            stark.verify_proof(
+                &truncated,
+                &transition_constraints,
+                &boundary_constraints,
+                &[]
+            ),
+            Err(StarkError::MalformedProof)
+        );
+
+        let mut truncated = proof;
+        truncated.boundary_openings[1].pop();
+        assert_eq!(
+            stark.verify_proof(
+                &truncated,
+                &transition_constraints,
+                &boundary_constraints,
+                &[]
+            ),
+            Err(StarkError::MalformedProof)
+        );
+    }
+
+    #[test]
+    fn should_derive_config_from_security_bits() {
+        let config = StarkConfig::<CryptoBigIntElement>::for_security_bits(80);
+        // 5 bits per query at an expansion factor of 32
+        assert_eq!(config.expansion_factor, 32);
+        assert_eq!(config.colinearity_test_count, 16);
+        assert_eq!(config.randomizer_count, 64);
+        assert!(config.security_level >= 80);
+
+        // rounds up to a whole query
+        let config = StarkConfig::<CryptoBigIntElement>::for_security_bits(81);
+        assert_eq!(config.colinearity_test_count, 17);
+        assert_eq!(config.security_level, 85);
+
+        let (stark, _, _, _) = squares_stark();
+        let stark = Stark::with_config(stark.field(), 2, 40, &StarkConfig::for_security_bits(80));
+        // no offset in the config uses the field generator
+        assert_eq!(&stark.offset, stark.field().g());
+        assert_eq!(stark.fri.colinearity_test_count, 16);
+        assert_eq!(stark.fri.expansion_factor, 32);
+        assert_eq!(stark.randomizer_count, 64);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn should_extend_same_in_parallel() {
+        let (stark, trace, _, _) = squares_stark();
+        let f = stark.field().clone();
+        let domain = stark.omicron_domain[0..trace.len()].to_vec();
+        let y_vals: Vec<Vec<CryptoBigIntElement>> = (0..2)
+            .map(|i| trace.iter().map(|r| r[i].clone()).collect())
+            .collect();
+        let polys = Polynomial::interpolate_fft_batch(&domain, &y_vals[0..], &f);
+        let serial = stark.extend_serial(&polys);
+        let parallel = stark.extend_parallel(&polys);
+        assert_eq!(serial.len(), 2);
+        assert_eq!(
+            serial[0].len(),
+            usize::try_from(stark.fri_domain_len).unwrap()
+        );
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn should_prove_column_trace() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let columns: Vec<Vec<CryptoBigIntElement>> = (0..2)
+            .map(|i| trace.iter().map(|r| r[i].clone()).collect())
+            .collect();
+        assert_eq!(
+            Stark::<CryptoBigIntElement>::transpose(&columns),
+            Ok(trace.clone())
+        );
+
+        // the randomizer rows differ so the proofs do too, but
+        // both verify against the same statement
+        let proof = stark
+            .prove_columns(
+                &columns,
+                &transition_constraints,
+                &boundary_constraints,
+                &[],
+            )
+            .unwrap();
+        assert_eq!(
+            stark.verify(&proof, &transition_constraints, &boundary_constraints, &[]),
+            Ok(())
+        );
+
+        let mut ragged = columns.clone();
+        ragged[1].pop();
+        assert_eq!(
+            stark.prove_columns(&ragged, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::InconsistentColumns {
+                column: 1,
+                len: 39,
+                expected: 40
+            })
+        );
+    }
+
+    #[test]
+    fn should_expose_trace_codewords() {
+        let (stark, trace, _, _) = squares_stark();
+        let f = stark.field().clone();
+        let domain = stark.evaluation_domain();
+        let codewords = stark.trace_codewords(&trace);
+        assert_eq!(codewords.len(), 2);
+
+        // each register has degree below the trace length, so that
+        // many points of its codeword determine it
+        let xs = domain[0..trace.len()].to_vec();
+        for (register, codeword) in codewords.iter().enumerate() {
+            assert_eq!(codeword.len(), domain.len());
+            let poly = Polynomial::lagrange(&xs, &codeword[0..trace.len()].to_vec(), &f);
+            for (row, registers) in trace.iter().enumerate() {
+                assert_eq!(poly.eval(&stark.omicron_domain[row]), registers[register]);
+            }
+            let last = domain.len() - 1;
+            assert_eq!(poly.eval(&domain[last]), codeword[last]);
+        }
+    }
+
+    #[test]
+    fn should_estimate_stark_proof_size() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let proof = stark
+            .prove_to_proof(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        let actual = proof.proof_byte_len();
+        let expected = stark.expected_proof_size();
+        assert_eq!(actual, proof.to_bytes().len());
+        // the estimate leaves out the length prefixes
+        assert!(expected <= actual);
+        assert!(actual - expected < actual / 100);
+    }
+
+    #[test]
+    fn should_prove_with_periodic_column() {
+        let (stark, _, _, _) = squares_stark();
+        let f = stark.field().clone();
+        let sequence_len = 40;
+        let mut stark = Stark::new(&stark.offset, &f, 1, sequence_len, 32, 26, 2);
+        // the register doubles on even cycles
+        let selector = vec![f.bigint(2), f.one()];
+        stark
+            .set_periodic_columns(std::slice::from_ref(&selector))
+            .unwrap();
+
+        let mut trace = vec![vec![f.bigint(3)]];
+        while trace.len() < sequence_len.try_into().unwrap() {
+            let i = trace.len() - 1;
+            trace.push(vec![f.mul(&trace[i][0], &selector[i % 2])]);
+        }
+        let boundary_constraints = vec![
+            (0, 0, f.bigint(3)),
+            (sequence_len - 1, 0, trace[trace.len() - 1][0].clone()),
+        ];
+
+        // next = prev * selector
+        let x = |i| MPolynomial::variable(i, &f);
+        let transition_constraints = vec![x(2).minus(&x(1).times(&x(3)))];
+
+        let proof = stark
+            .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        assert_eq!(
+            stark.verify(&proof, &transition_constraints, &boundary_constraints, &[]),
+            Ok(())
+        );
+
+        // a verifier with another column rejects the proof
+        stark
+            .set_periodic_columns(&[vec![f.one(), f.bigint(2)]])
+            .unwrap();
+        assert!(stark
+            .verify(&proof, &transition_constraints, &boundary_constraints, &[])
+            .is_err());
+        assert_eq!(
+            stark.prove(&trace, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::UnsatisfiedConstraint {
+                constraint: 0,
+                row: 0
+            })
+        );
+
+        assert_eq!(
+            stark.set_periodic_columns(&[vec![f.one(); 3]]),
+            Err(StarkError::InvalidPeriodicColumn { column: 0, len: 3 })
+        );
+    }
+
+    #[test]
+    fn should_reject_ragged_trace() {
+        let (stark, mut trace, transition_constraints, boundary_constraints) = squares_stark();
+        trace[5].pop();
+        assert_eq!(
+            stark.prove(&trace, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::InconsistentTrace {
+                row: 5,
+                len: 1,
+                expected: 2
+            })
+        );
+
+        trace.truncate(5);
+        assert_eq!(
+            stark.prove(&trace, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::TraceLength {
+                len: 5,
+                expected: 40
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_boundary() {
+        let (stark, trace, transition_constraints, mut boundary_constraints) = squares_stark();
+        let v = boundary_constraints[0].2.clone();
+        boundary_constraints.push((0, 2, v.clone()));
+        assert_eq!(
+            stark.prove(&trace, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::InvalidBoundary {
+                cycle: 0,
+                register: 2
+            })
+        );
+
+        boundary_constraints.pop();
+        boundary_constraints.push((40, 0, v));
+        assert_eq!(
+            stark.prove(&trace, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::InvalidBoundary {
+                cycle: 40,
+                register: 0
+            })
+        );
+    }
+
+    #[test]
+    fn should_dedup_boundary() {
+        let (stark, trace, transition_constraints, mut boundary_constraints) = squares_stark();
+        let f = stark.field().clone();
+
+        // a repeat of an existing entry is dropped
+        let entry = boundary_constraints[1].clone();
+        boundary_constraints.push(entry);
+        let proof = stark
+            .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        boundary_constraints.pop();
+        assert_eq!(
+            stark.verify(&proof, &transition_constraints, &boundary_constraints, &[]),
+            Ok(())
+        );
+
+        boundary_constraints.push((0, 1, f.bigint(4)));
+        assert_eq!(
+            stark.prove(&trace, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::ConflictingBoundary {
+                cycle: 0,
+                register: 1
+            })
+        );
+        assert_eq!(
+            stark.verify(&proof, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::ConflictingBoundary {
+                cycle: 0,
+                register: 1
+            })
+        );
+    }
+
+    #[test]
+    fn should_check_trace() {
+        let (stark, mut trace, transition_constraints, mut boundary_constraints) = squares_stark();
+        let f = stark.field().clone();
+        assert_eq!(
+            stark.check_trace(&trace, &transition_constraints, &boundary_constraints),
+            Ok(())
+        );
+
+        // breaking register 1 at row 20 breaks the second
+        // constraint between rows 19 and 20
+        trace[20][1] = f.bigint(7);
+        assert_eq!(
+            stark.check_trace(&trace, &transition_constraints, &boundary_constraints),
+            Err(ConstraintViolation::Transition {
+                row: 19,
+                constraint: 1
+            })
+        );
+
+        trace[20][1] = f.mul(&trace[19][1], &trace[19][1]);
+        boundary_constraints[2].2 = f.bigint(7);
+        assert_eq!(
+            stark.check_trace(&trace, &transition_constraints, &boundary_constraints),
+            Err(ConstraintViolation::Boundary {
+                row: 39,
+                constraint: 2
+            })
+        );
+        assert_eq!(
+            stark.prove(&trace, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::UnsatisfiedBoundary {
+                cycle: 39,
+                register: 0
+            })
+        );
+    }
+
+    #[test]
+    fn should_bound_constraint_degrees() {
+        let (stark, trace, mut transition_constraints, boundary_constraints) = squares_stark();
+        let f = stark.field().clone();
+        let x = MPolynomial::variables(5, &f);
+
+        // 40 rows and 104 randomizers interpolate to degree 143,
+        // and the zeroifier removes 39
+        let cube = x[1].times(&x[1]).times(&x[1]).minus(&x[3]);
+        transition_constraints.push(cube.clone());
+        assert_eq!(
+            stark.constraint_degree_bounds(&transition_constraints),
+            vec![2 * 143 - 39, 2 * 143 - 39, 3 * 143 - 39]
+        );
+
+        // degree 4 doesn't fit the 512 point omicron domain
+        transition_constraints.pop();
+        transition_constraints.push(cube.times(&x[1]));
+        assert_eq!(
+            stark.prove(&trace, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::ConstraintDegree {
+                constraint: 2,
+                degree: 4 * 143 - 39,
+                max: 511
+            })
+        );
+    }
+
+    #[test]
+    fn should_require_transition_constraints() {
+        let (stark, trace, transition_constraints, boundary_constraints) = squares_stark();
+        let proof = stark
+            .prove(&trace, &transition_constraints, &boundary_constraints, &[])
+            .unwrap();
+        assert_eq!(
+            stark.prove(&trace, &vec![], &boundary_constraints, &[]),
+            Err(StarkError::NoTransitionConstraints)
+        );
+        assert_eq!(
+            stark.verify(&proof, &vec![], &boundary_constraints, &[]),
+            Err(StarkError::NoTransitionConstraints)
+        );
+    }
+
+    #[test]
+    fn should_reject_mismatched_constraints() {
+        let (stark, mut trace, mut transition_constraints, boundary_constraints) = squares_stark();
+        let f = stark.field().clone();
+
+        // x5 doesn't exist for a two register trace
+        let extra = MPolynomial::variable(5, &f);
+        transition_constraints.push(extra);
+        assert_eq!(
+            stark.prove(&trace, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::ConstraintVariables { count: 6, max: 5 })
+        );
+
+        transition_constraints.pop();
+        trace[10][0] = f.bigint(7);
+        assert_eq!(
+            stark.prove(&trace, &transition_constraints, &boundary_constraints, &[]),
+            Err(StarkError::UnsatisfiedConstraint {
+                constraint: 0,
+                row: 9
+            })
+        );
+    }
 }
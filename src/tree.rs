@@ -1,34 +1,235 @@
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::marker::PhantomData;
 
 use crate::FieldElement;
 
-pub struct Tree<T: FieldElement> {
-    pub levels: Vec<Vec<[u8; 32]>>,
-    data: PhantomData<T>,
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeError {
+    // a path node doesn't match the hash computed below it
+    IntermediateMismatch { level: usize },
+    // the path hashes to a different root
+    RootMismatch,
+    // the path length isn't a multiple of the siblings per level
+    InvalidPathLength,
 }
 
-impl<T: FieldElement> Tree<T> {
-    pub fn hash(leaf1: &[u8; 32], leaf2: &[u8; 32]) -> [u8; 32] {
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TreeError::IntermediateMismatch { level } => {
+                write!(f, "invalid intermediate root at level {}", level)
+            }
+            TreeError::RootMismatch => write!(f, "root mismatch"),
+            TreeError::InvalidPathLength => write!(f, "invalid path length"),
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+// leaves and internal nodes must be hashed differently so
+// an internal node can't be presented as a leaf
+pub trait TreeHasher {
+    fn hash_leaf_bytes(leaf: &[u8]) -> [u8; 32];
+    fn hash_leaf(leaf: &[u8; 32]) -> [u8; 32] {
+        Self::hash_leaf_bytes(leaf)
+    }
+    // combines two child nodes into their parent
+    fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32];
+    // combines any number of child nodes, must match
+    // hash_pair for two children
+    fn hash_children(children: &[[u8; 32]]) -> [u8; 32] {
+        let mut out = children[0];
+        for child in &children[1..] {
+            out = Self::hash_pair(&out, child);
+        }
+        out
+    }
+}
+
+// domain separation prefixes
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+pub struct Blake3Hasher;
+
+impl TreeHasher for Blake3Hasher {
+    fn hash_leaf_bytes(leaf: &[u8]) -> [u8; 32] {
         let mut hasher = blake3::Hasher::new();
-        hasher.update(leaf1);
-        hasher.update(leaf2);
+        hasher.update(&[LEAF_PREFIX]);
+        hasher.update(leaf);
         *hasher.finalize().as_bytes()
     }
 
+    fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        Self::hash_children(&[*left, *right])
+    }
+
+    fn hash_children(children: &[[u8; 32]]) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&[NODE_PREFIX]);
+        for child in children {
+            hasher.update(child);
+        }
+        *hasher.finalize().as_bytes()
+    }
+}
+
+// keccak256 for commitments that are checked on ethereum
+pub struct KeccakHasher;
+
+impl TreeHasher for KeccakHasher {
+    fn hash_leaf_bytes(leaf: &[u8]) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update([LEAF_PREFIX]);
+        hasher.update(leaf);
+        hasher.finalize().into()
+    }
+
+    fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        Self::hash_children(&[*left, *right])
+    }
+
+    fn hash_children(children: &[[u8; 32]]) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update([NODE_PREFIX]);
+        for child in children {
+            hasher.update(child);
+        }
+        hasher.finalize().into()
+    }
+}
+
+// siblings needed to verify several leaves at once, one
+// list per level. nodes shared between paths are only
+// included once and nodes that can be computed are omitted
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiProof {
+    pub levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MultiProof {
+    pub fn node_count(&self) -> usize {
+        self.levels.iter().map(|level| level.len()).sum()
+    }
+}
+
+// a single authentication path, as returned by open
+// encoded as index (u32 le) followed by 32 byte nodes
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerkleProof {
+    pub index: u32,
+    pub path: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.index.to_le_bytes().to_vec();
+        for node in &self.path {
+            out.extend_from_slice(node);
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<MerkleProof> {
+        if bytes.len() < 4 || !(bytes.len() - 4).is_multiple_of(32) {
+            return None;
+        }
+        let index = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+        let path = bytes[4..]
+            .chunks(32)
+            .map(|node| node.try_into().unwrap())
+            .collect();
+        Some(MerkleProof { index, path })
+    }
+}
+
+// serialized as one hex string instead of a list of numbers
+impl Serialize for MerkleProof {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let hex: String = self
+            .to_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        serializer.serialize_str(&hex)
+    }
+}
+
+impl<'de> Deserialize<'de> for MerkleProof {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        if !s.len().is_multiple_of(2) || !s.is_ascii() {
+            return Err(serde::de::Error::custom("invalid hex proof"));
+        }
+        let bytes = (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..(i + 2)], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| serde::de::Error::custom("invalid hex proof"))?;
+        MerkleProof::from_bytes(&bytes)
+            .ok_or_else(|| serde::de::Error::custom("invalid merkle proof length"))
+    }
+}
+
+pub struct Tree<T: FieldElement, H: TreeHasher = Blake3Hasher> {
+    pub levels: Vec<Vec<[u8; 32]>>,
+    // children per internal node
+    arity: usize,
+    // number of leaves, not including padding
+    leaf_count: usize,
+    data: PhantomData<(T, H)>,
+}
+
+impl<T: FieldElement, H: TreeHasher> Tree<T, H> {
+    pub fn hash(leaf1: &[u8; 32], leaf2: &[u8; 32]) -> [u8; 32] {
+        H::hash_pair(leaf1, leaf2)
+    }
+
     pub fn root(&self) -> [u8; 32] {
         self.levels[self.levels.len() - 1][0]
     }
 
+    pub fn hash_leaf(leaf: &[u8; 32]) -> [u8; 32] {
+        H::hash_leaf(leaf)
+    }
+
+    // hashes of the leaves, the bottom level of the tree
     pub fn leaves(&self) -> &Vec<[u8; 32]> {
         &self.levels[0]
     }
 
-    pub fn build(leaves: &Vec<[u8; 32]>) -> Tree<T> {
-        let mut levels = Vec::new();
-        levels.push(leaves.clone());
+    pub fn build(leaves: &Vec<[u8; 32]>) -> Tree<T, H> {
+        Self::build_from_hashes(leaves.iter().map(Self::hash_leaf).collect())
+    }
+
+    // leaves of any length, a 32 byte leaf hashes the same as in build
+    pub fn build_bytes(leaves: &[Vec<u8>]) -> Tree<T, H> {
+        Self::build_from_hashes(leaves.iter().map(|leaf| H::hash_leaf_bytes(leaf)).collect())
+    }
 
-        // zzzz
-        let level_count = (levels[0].len() as f32).log2().ceil() as usize;
+    pub fn commit_bytes(leaves: &[Vec<u8>]) -> [u8; 32] {
+        Self::build_bytes(leaves).root()
+    }
+
+    fn build_from_hashes(leaf_hashes: Vec<[u8; 32]>) -> Tree<T, H> {
+        let leaf_count = leaf_hashes.len();
+        let mut levels: Vec<Vec<[u8; 32]>> = vec![leaf_hashes];
+
+        // ceil(log2(len)) without going through floats
+        let level_count = match levels[0].len() {
+            0 | 1 => 0,
+            len => usize::try_from(usize::BITS - (len - 1).leading_zeros()).unwrap(),
+        };
 
         for i in 0..level_count {
             let mut level = Vec::new();
@@ -36,16 +237,120 @@ impl<T: FieldElement> Tree<T> {
                 levels[i].push(vec![0_u8; 32].try_into().unwrap());
             }
             for j in (0..levels[i].len()).step_by(2) {
-                level.push(Tree::<T>::hash(&levels[i][j], &levels[i][j + 1]));
+                level.push(Self::hash(&levels[i][j], &levels[i][j + 1]));
+            }
+            levels.push(level);
+        }
+        Tree {
+            levels,
+            arity: 2,
+            leaf_count,
+            data: PhantomData,
+        }
+    }
+
+    // append a leaf, only the nodes above it are rehashed
+    // the result matches build over all the leaves
+    pub fn push(&mut self, leaf: &[u8; 32]) {
+        if self.arity != 2 {
+            panic!("push requires a binary tree");
+        }
+        self.levels[0].truncate(self.leaf_count);
+        self.levels[0].push(Self::hash_leaf(leaf));
+        self.leaf_count += 1;
+
+        let mut count = self.leaf_count;
+        let mut index = count - 1;
+        let mut i = 0;
+        while count > 1 {
+            // drop old padding and pad to an even length again
+            self.levels[i].truncate(count);
+            if count % 2 == 1 {
+                self.levels[i].push([0_u8; 32]);
             }
+            if self.levels.len() == i + 1 {
+                self.levels.push(Vec::new());
+            }
+            let parent = index >> 1;
+            let node = Self::hash(&self.levels[i][2 * parent], &self.levels[i][2 * parent + 1]);
+            self.levels[i + 1].truncate(parent);
+            self.levels[i + 1].push(node);
+            index = parent;
+            count = parent + 1;
+            i += 1;
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    // each level is padded with zero nodes to a multiple of arity
+    pub fn build_arity(leaves: &[[u8; 32]], arity: usize) -> Tree<T, H> {
+        if arity < 2 {
+            panic!("tree arity must be at least 2");
+        }
+        let mut levels: Vec<Vec<[u8; 32]>> = Vec::new();
+        levels.push(leaves.iter().map(Self::hash_leaf).collect());
+        while levels[levels.len() - 1].len() > 1 {
+            let i = levels.len() - 1;
+            let padded_len = levels[i].len().div_ceil(arity) * arity;
+            levels[i].resize(padded_len, [0_u8; 32]);
+            let level = levels[i].chunks(arity).map(H::hash_children).collect();
             levels.push(level);
         }
         Tree {
             levels,
+            arity,
+            leaf_count: leaves.len(),
             data: PhantomData,
         }
     }
 
+    // arity - 1 siblings per level, in order, without the node itself
+    pub fn open_arity(&self, index: u32) -> (Vec<[u8; 32]>, [u8; 32]) {
+        let mut index = usize::try_from(index).unwrap();
+        if index >= self.leaves().len() {
+            panic!("index is greater than leaves length");
+        }
+        let mut path = Vec::new();
+        for i in 0..(self.levels.len() - 1) {
+            let start = index - index % self.arity;
+            for j in start..(start + self.arity) {
+                if j != index {
+                    path.push(self.levels[i][j]);
+                }
+            }
+            index /= self.arity;
+        }
+        (path, self.root())
+    }
+
+    pub fn verify_arity(
+        root: &[u8; 32],
+        index: u32,
+        path: &[[u8; 32]],
+        leaf: &[u8; 32],
+        arity: usize,
+    ) -> Result<(), TreeError> {
+        if arity < 2 || !path.len().is_multiple_of(arity - 1) {
+            return Err(TreeError::InvalidPathLength);
+        }
+        let mut index = usize::try_from(index).unwrap();
+        let mut calculated_root = Self::hash_leaf(leaf);
+        for siblings in path.chunks(arity - 1) {
+            let position = index % arity;
+            let mut children = siblings.to_vec();
+            children.insert(position, calculated_root);
+            calculated_root = H::hash_children(&children);
+            index /= arity;
+        }
+        if &calculated_root != root {
+            return Err(TreeError::RootMismatch);
+        }
+        Ok(())
+    }
+
     pub fn commit_elements(leaves: &[T]) -> [u8; 32] {
         Self::commit(
             &leaves
@@ -61,8 +366,11 @@ impl<T: FieldElement> Tree<T> {
     }
 
     pub fn open(&self, index: u32) -> (Vec<[u8; 32]>, [u8; 32]) {
+        if self.arity != 2 {
+            panic!("open requires a binary tree, use open_arity");
+        }
         let mut index = index;
-        if index > self.leaves().len().try_into().unwrap() {
+        if index >= self.leaves().len().try_into().unwrap() {
             panic!("index is greater than leaves length");
         }
         let mut path = Vec::new();
@@ -82,21 +390,146 @@ impl<T: FieldElement> Tree<T> {
         (path, self.root())
     }
 
-    pub fn verify(root: &[u8; 32], _index: u32, path: &[[u8; 32]], leaf: &[u8; 32]) -> bool {
-        let mut index = _index;
-        let mut calculated_root = *leaf;
-        for p in path.chunks(2) {
+    pub fn open_proof(&self, index: u32) -> MerkleProof {
+        let (path, _) = self.open(index);
+        MerkleProof { index, path }
+    }
+
+    pub fn verify_proof(
+        root: &[u8; 32],
+        proof: &MerkleProof,
+        leaf: &[u8; 32],
+    ) -> Result<(), TreeError> {
+        Self::verify(root, proof.index, &proof.path, leaf)
+    }
+
+    pub fn open_many(&self, indices: &[u32]) -> MultiProof {
+        if self.arity != 2 {
+            panic!("open_many requires a binary tree");
+        }
+        let leaf_count: u32 = self.leaves().len().try_into().unwrap();
+        let mut known: Vec<u32> = indices.to_vec();
+        known.sort();
+        known.dedup();
+        if let Some(index) = known.last() {
+            if *index >= leaf_count {
+                panic!("index is greater than leaves length");
+            }
+        }
+        let mut levels = Vec::new();
+        for i in 0..(self.levels.len() - 1) {
+            let mut siblings = Vec::new();
+            let mut parents = Vec::new();
+            for index in &known {
+                let sibling_index = index ^ 1;
+                // known is sorted so a known sibling is adjacent
+                if known.binary_search(&sibling_index).is_err() {
+                    siblings.push(self.levels[i][usize::try_from(sibling_index).unwrap()]);
+                }
+                if parents.last() != Some(&(index >> 1)) {
+                    parents.push(index >> 1);
+                }
+            }
+            levels.push(siblings);
+            known = parents;
+        }
+        MultiProof { levels }
+    }
+
+    // leaves[i] is the leaf at indices[i]
+    pub fn verify_many(
+        root: &[u8; 32],
+        indices: &[u32],
+        proof: &MultiProof,
+        leaves: &[[u8; 32]],
+    ) -> bool {
+        if indices.len() != leaves.len() || indices.is_empty() {
+            return false;
+        }
+        let mut known = BTreeMap::new();
+        for (index, leaf) in indices.iter().zip(leaves) {
+            let leaf_hash = Self::hash_leaf(leaf);
+            if let Some(existing) = known.insert(*index, leaf_hash) {
+                if existing != leaf_hash {
+                    return false;
+                }
+            }
+        }
+        for siblings in &proof.levels {
+            let mut siblings = siblings.iter();
+            let mut parents = BTreeMap::new();
+            for (index, node) in &known {
+                let sibling_index = index ^ 1;
+                let sibling = match known.get(&sibling_index) {
+                    Some(sibling) => *sibling,
+                    None => match siblings.next() {
+                        Some(sibling) => *sibling,
+                        None => return false,
+                    },
+                };
+                let parent = if index % 2 == 0 {
+                    Self::hash(node, &sibling)
+                } else {
+                    Self::hash(&sibling, node)
+                };
+                parents.insert(index >> 1, parent);
+            }
+            if siblings.next().is_some() {
+                return false;
+            }
+            known = parents;
+        }
+        known.len() == 1 && known.get(&0) == Some(root)
+    }
+
+    pub fn verify(
+        root: &[u8; 32],
+        index: u32,
+        path: &[[u8; 32]],
+        leaf: &[u8; 32],
+    ) -> Result<(), TreeError> {
+        Self::verify_hash(root, index, path, &Self::hash_leaf(leaf))
+    }
+
+    pub fn verify_bytes(
+        root: &[u8; 32],
+        index: u32,
+        path: &[[u8; 32]],
+        leaf: &[u8],
+    ) -> Result<(), TreeError> {
+        Self::verify_hash(root, index, path, &H::hash_leaf_bytes(leaf))
+    }
+
+    fn verify_hash(
+        root: &[u8; 32],
+        index: u32,
+        path: &[[u8; 32]],
+        leaf_hash: &[u8; 32],
+    ) -> Result<(), TreeError> {
+        // each level is a pair of siblings
+        if !path.len().is_multiple_of(2) {
+            return Err(TreeError::InvalidPathLength);
+        }
+        let mut index = index;
+        let mut calculated_root = *leaf_hash;
+        for (level, p) in path.chunks(2).enumerate() {
             let node_index = index % 2;
             if p[node_index as usize] != calculated_root {
-                panic!("Invalid intermediate root");
+                return Err(TreeError::IntermediateMismatch { level });
             }
             calculated_root = Self::hash(&p[0], &p[1]);
             index >>= 1;
         }
         if &calculated_root != root {
-            panic!("root mismatch");
+            return Err(TreeError::RootMismatch);
+        }
+        Ok(())
+    }
+
+    pub fn verify_or_panic(root: &[u8; 32], index: u32, path: &[[u8; 32]], leaf: &[u8; 32]) {
+        if let Err(e) = Self::verify(root, index, path, leaf) {
+            panic!("{}", e);
         }
-        true
     }
 }
 
@@ -155,7 +588,10 @@ mod tests {
         let index = 5;
         let tree = Tree::<CryptoBigIntElement>::build(&leaves);
         let (path, root) = tree.open(index);
-        Tree::<CryptoBigIntElement>::verify(&root, index, &path, &leaves[index as usize]);
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify(&root, index, &path, &leaves[index as usize]),
+            Ok(())
+        );
     }
 
     #[test]
@@ -170,7 +606,7 @@ mod tests {
         let (mut path, root) = tree.open(index);
         // change some path element
         path[4] = u128_to_bytes(&124812491);
-        Tree::<CryptoBigIntElement>::verify(&root, index, &path, &leaves[index as usize]);
+        Tree::<CryptoBigIntElement>::verify_or_panic(&root, index, &path, &leaves[index as usize]);
     }
 
     #[test]
@@ -184,6 +620,304 @@ mod tests {
         let index = 5;
         let (path, _) = tree.open(index);
         let root = u128_to_bytes(&1921);
-        Tree::<CryptoBigIntElement>::verify(&root, index, &path, &leaves[index as usize]);
+        Tree::<CryptoBigIntElement>::verify_or_panic(&root, index, &path, &leaves[index as usize]);
+    }
+
+    #[test]
+    fn should_open_stored_tree_repeatedly() {
+        let mut leaves = Vec::new();
+        for i in 0..100 {
+            leaves.push(u128_to_bytes(&(i as u128)));
+        }
+        let tree = Tree::<CryptoBigIntElement>::build(&leaves);
+        for _ in 0..3 {
+            for index in [0, 5, 63, 99] {
+                let fresh = Tree::<CryptoBigIntElement>::build(&leaves);
+                assert_eq!(tree.open(index), fresh.open(index));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_fail_to_open_out_of_range() {
+        let mut leaves = Vec::new();
+        for i in 0..100 {
+            leaves.push(u128_to_bytes(&(i as u128)));
+        }
+        let tree = Tree::<CryptoBigIntElement>::build(&leaves);
+        tree.open(100);
+    }
+
+    #[test]
+    fn should_change_root_with_hasher() {
+        // blake3 over the children in reverse order
+        struct ReversedHasher;
+        impl TreeHasher for ReversedHasher {
+            fn hash_leaf_bytes(leaf: &[u8]) -> [u8; 32] {
+                Blake3Hasher::hash_leaf_bytes(leaf)
+            }
+
+            fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+                Blake3Hasher::hash_pair(right, left)
+            }
+        }
+
+        let mut leaves = Vec::new();
+        for i in 0..100 {
+            leaves.push(u128_to_bytes(&(i as u128)));
+        }
+        let root = Tree::<CryptoBigIntElement>::commit(&leaves);
+        let blake3_root = Tree::<CryptoBigIntElement, Blake3Hasher>::commit(&leaves);
+        let reversed_root = Tree::<CryptoBigIntElement, ReversedHasher>::commit(&leaves);
+        assert_eq!(root, blake3_root);
+        assert_ne!(root, reversed_root);
+
+        let index = 5;
+        let tree = Tree::<CryptoBigIntElement, ReversedHasher>::build(&leaves);
+        let (path, root) = tree.open(index);
+        assert!(Tree::<CryptoBigIntElement, ReversedHasher>::verify(
+            &root,
+            index,
+            &path,
+            &leaves[index as usize]
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn should_open_verify_many() {
+        let mut leaves = Vec::new();
+        for i in 0..64 {
+            leaves.push(u128_to_bytes(&(i as u128)));
+        }
+        let tree = Tree::<CryptoBigIntElement>::build(&leaves);
+        let indices = [3, 2, 17, 40, 41, 63, 0, 17];
+        let proof = tree.open_many(&indices);
+        let opened: Vec<[u8; 32]> = indices.iter().map(|i| leaves[*i as usize]).collect();
+        assert!(Tree::<CryptoBigIntElement>::verify_many(
+            &tree.root(),
+            &indices,
+            &proof,
+            &opened
+        ));
+
+        let individual_siblings: usize = indices.iter().map(|i| tree.open(*i).0.len() / 2).sum();
+        assert!(proof.node_count() < individual_siblings);
+
+        // tampered leaf
+        let mut tampered = opened.clone();
+        tampered[2] = u128_to_bytes(&124812491);
+        assert!(!Tree::<CryptoBigIntElement>::verify_many(
+            &tree.root(),
+            &indices,
+            &proof,
+            &tampered
+        ));
+        // tampered proof node
+        let mut tampered_proof = proof.clone();
+        tampered_proof.levels[2][0] = u128_to_bytes(&124812491);
+        assert!(!Tree::<CryptoBigIntElement>::verify_many(
+            &tree.root(),
+            &indices,
+            &tampered_proof,
+            &opened
+        ));
+    }
+
+    #[test]
+    fn should_return_verify_errors() {
+        let mut leaves = Vec::new();
+        for i in 0..100 {
+            leaves.push(u128_to_bytes(&(i as u128)));
+        }
+        let tree = Tree::<CryptoBigIntElement>::build(&leaves);
+        let index = 5;
+        let leaf = leaves[index as usize];
+        let (path, root) = tree.open(index);
+
+        // index 5 is the right node at level 2, path[5]
+        let mut tampered_path = path.clone();
+        tampered_path[5] = u128_to_bytes(&124812491);
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify(&root, index, &tampered_path, &leaf),
+            Err(TreeError::IntermediateMismatch { level: 2 })
+        );
+
+        let tampered_root = u128_to_bytes(&1921);
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify(&tampered_root, index, &path, &leaf),
+            Err(TreeError::RootMismatch)
+        );
+
+        let short_path = &path[..(path.len() - 1)];
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify(&root, index, short_path, &leaf),
+            Err(TreeError::InvalidPathLength)
+        );
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify(&root, 1, &[[1; 32]], &leaf),
+            Err(TreeError::InvalidPathLength)
+        );
+    }
+
+    #[test]
+    fn should_not_verify_internal_node_as_leaf() {
+        let mut leaves = Vec::new();
+        for i in 0..64 {
+            leaves.push(u128_to_bytes(&(i as u128)));
+        }
+        let tree = Tree::<CryptoBigIntElement>::build(&leaves);
+        let (path, root) = tree.open(0);
+        // present the parent of leaves 0 and 1 as leaf 0 of a
+        // tree one level shorter
+        let internal_node = tree.levels[1][0];
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify(&root, 0, &path[2..], &internal_node),
+            Err(TreeError::IntermediateMismatch { level: 0 })
+        );
+        let leaf_hash = Tree::<CryptoBigIntElement>::hash_leaf(&leaves[0]);
+        assert_ne!(leaf_hash, leaves[0]);
+    }
+
+    #[test]
+    fn should_open_verify_arity_tree() {
+        let mut leaves = Vec::new();
+        for i in 0..64 {
+            leaves.push(u128_to_bytes(&(i as u128)));
+        }
+        let tree = Tree::<CryptoBigIntElement>::build_arity(&leaves, 4);
+        // 64, 16, 4, 1
+        assert_eq!(tree.levels.len(), 4);
+        assert_eq!(tree.arity(), 4);
+
+        let index = 37;
+        let (path, root) = tree.open_arity(index);
+        assert_eq!(path.len(), 3 * 3);
+        let leaf = leaves[index as usize];
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify_arity(&root, index, &path, &leaf, 4),
+            Ok(())
+        );
+
+        let mut tampered_path = path.clone();
+        tampered_path[4] = u128_to_bytes(&124812491);
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify_arity(&root, index, &tampered_path, &leaf, 4),
+            Err(TreeError::RootMismatch)
+        );
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify_arity(&root, index, &path[1..], &leaf, 4),
+            Err(TreeError::InvalidPathLength)
+        );
+
+        // binary trees match build
+        let binary = Tree::<CryptoBigIntElement>::build_arity(&leaves, 2);
+        assert_eq!(binary.root(), Tree::<CryptoBigIntElement>::commit(&leaves));
+    }
+
+    #[test]
+    fn should_build_exact_level_count() {
+        for (len, expected_levels) in [(1, 1), (2, 2), (3, 3), (1024, 11), (1025, 12)] {
+            let mut leaves = Vec::new();
+            for i in 0..len {
+                leaves.push(u128_to_bytes(&(i as u128)));
+            }
+            let tree = Tree::<CryptoBigIntElement>::build(&leaves);
+            assert_eq!(tree.levels.len(), expected_levels);
+            assert_eq!(tree.levels[tree.levels.len() - 1].len(), 1);
+        }
+    }
+
+    #[test]
+    fn should_build_known_keccak_root() {
+        let leaves: Vec<[u8; 32]> = (0..4).map(|i| u128_to_bytes(&i)).collect();
+        let root = Tree::<CryptoBigIntElement, KeccakHasher>::commit(&leaves);
+        let root_hex: String = root.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            root_hex,
+            "2ee8d01d3ab0b6e85a9d60834637963a3d9d53cb44707501d9a73538c5cbf2fc"
+        );
+        assert_ne!(root, Tree::<CryptoBigIntElement>::commit(&leaves));
+    }
+
+    #[test]
+    fn should_serialize_merkle_proof() {
+        let mut leaves = Vec::new();
+        for i in 0..100 {
+            leaves.push(u128_to_bytes(&(i as u128)));
+        }
+        let tree = Tree::<CryptoBigIntElement>::build(&leaves);
+        let proof = tree.open_proof(37);
+
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes.len(), 4 + 32 * proof.path.len());
+        assert_eq!(MerkleProof::from_bytes(&bytes), Some(proof.clone()));
+        assert_eq!(MerkleProof::from_bytes(&bytes[1..]), None);
+
+        let json = serde_json::to_string(&proof).unwrap();
+        let decoded: MerkleProof = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, proof);
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify_proof(&tree.root(), &decoded, &leaves[37]),
+            Ok(())
+        );
+
+        // compared to the path as a list of byte arrays
+        let path_json = serde_json::to_string(&proof.path).unwrap();
+        assert!(json.len() < path_json.len());
+    }
+
+    #[test]
+    fn should_push_leaves_incrementally() {
+        let mut leaves = Vec::new();
+        let mut tree = Tree::<CryptoBigIntElement>::build(&leaves);
+        for i in 0..70 {
+            let leaf = u128_to_bytes(&(i as u128));
+            leaves.push(leaf);
+            tree.push(&leaf);
+            let built = Tree::<CryptoBigIntElement>::build(&leaves);
+            assert_eq!(tree.root(), Tree::<CryptoBigIntElement>::commit(&leaves));
+            assert_eq!(tree.levels, built.levels);
+        }
+        let (path, root) = tree.open(37);
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::verify(&root, 37, &path, &leaves[37]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_commit_byte_leaves() {
+        let leaves: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![1],
+            b"public input".to_vec(),
+            vec![7; 32],
+            vec![9; 1000],
+        ];
+        let tree = Tree::<CryptoBigIntElement>::build_bytes(&leaves);
+        let root = Tree::<CryptoBigIntElement>::commit_bytes(&leaves);
+        assert_eq!(tree.root(), root);
+        for (i, leaf) in leaves.iter().enumerate() {
+            let index = u32::try_from(i).unwrap();
+            let (path, root) = tree.open(index);
+            assert_eq!(
+                Tree::<CryptoBigIntElement>::verify_bytes(&root, index, &path, leaf),
+                Ok(())
+            );
+        }
+        let mut changed = leaves.clone();
+        changed[4][999] = 0;
+        let changed_root = Tree::<CryptoBigIntElement>::commit_bytes(&changed);
+        assert_ne!(root, changed_root);
+
+        // 32 byte leaves match the fixed size api
+        let fixed: Vec<[u8; 32]> = (0..5).map(|i| u128_to_bytes(&i)).collect();
+        let as_bytes: Vec<Vec<u8>> = fixed.iter().map(|leaf| leaf.to_vec()).collect();
+        assert_eq!(
+            Tree::<CryptoBigIntElement>::commit_bytes(&as_bytes),
+            Tree::<CryptoBigIntElement>::commit(&fixed)
+        );
     }
 }